@@ -0,0 +1,76 @@
+//! Compares `OrderBook::update` against the previous `BTreeSet` storage on depth-5 snapshots.
+//!
+//! Run with `cargo bench --bench update`.
+
+use std::collections::BTreeSet;
+use std::hint::black_box;
+use criterion::{criterion_group, criterion_main, Criterion};
+use warmup_project::item::Item;
+use warmup_project::order_book::OrderBook;
+
+/// The update path as it was with `BTreeSet` sides: clear, then insert every level.
+#[derive(Default)]
+struct BTreeSetBook {
+    bids: BTreeSet<Item>,
+    asks: BTreeSet<Item>,
+}
+
+impl BTreeSetBook {
+    fn update(&mut self, bids: Vec<(f64, i64)>, asks: Vec<(f64, i64)>, depth: usize) {
+        self.bids.clear();
+        self.asks.clear();
+        for (price, size) in bids.into_iter().filter(|level| level.0.is_finite() && level.1 > 0) {
+            self.bids.insert(Item { price, size });
+        }
+        for (price, size) in asks.into_iter().filter(|level| level.0.is_finite() && level.1 > 0) {
+            self.asks.insert(Item { price, size });
+        }
+        while self.bids.len() > depth {
+            self.bids.pop_first();
+        }
+        while self.asks.len() > depth {
+            self.asks.pop_last();
+        }
+    }
+}
+
+/// Bid and ask `(price, size)` levels.
+type Levels = (Vec<(f64, i64)>, Vec<(f64, i64)>);
+
+/// A depth-5 snapshot shaped like the KuCoin feed, shifted by `tick`.
+fn snapshot(tick: u64) -> Levels {
+    let base = 2000.0 + (tick % 7) as f64 * 0.01;
+    let bids = (0..5).map(|i| (base - i as f64 * 0.01, 10 + i)).collect();
+    let asks = (0..5).map(|i| (base + 0.01 + i as f64 * 0.01, 20 + i)).collect();
+    (bids, asks)
+}
+
+fn bench_update(c: &mut Criterion) {
+    let snapshots: Vec<_> = (0..64).map(snapshot).collect();
+    let mut group = c.benchmark_group("depth5_update");
+
+    group.bench_function("sorted_vec", |b| {
+        let mut ob = OrderBook::new();
+        let mut tick = 0;
+        b.iter(|| {
+            let (bids, asks) = snapshots[tick % snapshots.len()].clone();
+            ob.update(black_box(bids), black_box(asks));
+            tick += 1;
+        })
+    });
+
+    group.bench_function("btreeset", |b| {
+        let mut ob = BTreeSetBook::default();
+        let mut tick = 0;
+        b.iter(|| {
+            let (bids, asks) = snapshots[tick % snapshots.len()].clone();
+            ob.update(black_box(bids), black_box(asks), 5);
+            tick += 1;
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_update);
+criterion_main!(benches);
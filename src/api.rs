@@ -1,198 +1,1225 @@
 use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use serde_json::Value;
-use std::error::Error;
-use tokio_tungstenite::connect_async;
-use futures_util::{StreamExt, SinkExt};
-use tokio_tungstenite::tungstenite::protocol::Message;
-use crate::order_book::OrderBook;
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::{client_async_tls_with_config, connect_async_with_config, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use futures_util::{Stream, StreamExt, SinkExt};
+use futures_util::stream::{SplitSink, SplitStream};
+use tokio_tungstenite::tungstenite::protocol::{Message, WebSocketConfig};
+use tokio_tungstenite::tungstenite::Error as WsError;
+use crate::config::{FeedConfig, Market, OutputFormat, PrintConfig};
+use crate::error::FeedError;
+use crate::format::in_place;
+use crate::exchange::Exchange;
+use crate::order_book::{OrderBook, SequenceCheck, UpdateReport};
+use crate::parse::coerce_f64;
+use crate::proxy::socks5_connect;
+use crate::snapshot::{OrderBookSnapshot, TopOfBookChange};
+use crate::emit::{snapshot_channel, SnapshotSender};
+use crate::stats::ListenerStats;
+use crate::status::SystemStatus;
+use crate::symbol::Symbol;
+use crate::telemetry;
 
-/// Updates the order book with new bid and ask data from a JSON response.
+/// Establishes a WebSocket connection to the KuCoin API and listens for real-time order book updates.
 ///
-/// This function extracts the top 5 bid and ask levels from the given JSON data,
-/// converts them into floating-point price levels and integer sizes, and updates
-/// the provided `OrderBook` instance accordingly.
+/// This function:
+/// - Requests a **WebSocket token** from the KuCoin API.
+/// - Extracts the **WebSocket URL** and establishes a **secure connection**.
+/// - **Subscribes to order book updates** for `config.symbol`.
+/// - **Processes and applies market updates** to the provided `OrderBook`.
+/// - **Reconnects** with a fresh token when the connection is lost, as allowed by `config.reconnect`.
 ///
 /// # Arguments
 ///
-/// * `ob` - A mutable reference to an `OrderBook` instance where the parsed bid and ask data will be stored.
-/// * `json_data` - A `serde_json::Value` containing order book data, expected to have `"bids"` and `"asks"` fields.
+/// * `ob` - A mutable reference to an `OrderBook` instance that will be updated in real time.
+/// * `config` - The [`FeedConfig`] selecting the symbol, market, depth, endpoints and output.
+/// * `client` - An optional `reqwest` [`Client`] for the token requests, e.g. one configured with
+///   a proxy, timeouts or custom certificates. A default client is built when `None`.
+/// * `sink` - An optional [`SnapshotSender`] that receives a snapshot after every applied update,
+///   either queued or, from [`crate::emit::latest_snapshot_channel`], as the latest state only.
 ///
-/// # Behavior
+/// # Returns
 ///
-/// - Extracts up to **5 bid levels** and **5 ask levels** from the `json_data`.
-/// - Tries to parse **prices as `f64`** and **sizes as `i64`**, handling cases where values are stored as strings.
-/// - Calls `ob.update()` to apply the new bid and ask data.
-/// - Calls `ob.print()` to display the updated order book.
+/// Returns the final [`ListenerStats`] on success, or a [`FeedError`] describing what failed.
+/// Once reconnects are exhausted the last failure is wrapped in [`FeedError::ReconnectExhausted`].
 ///
-/// # Example JSON Input
+/// # Behavior
 ///
-/// ```json
-/// {
-///   "data": {
-///     "bids": [["60000.0", "1"], ["59950.0", "2"]],
-///     "asks": [["60100.0", "1"], ["60150.0", "3"]]
-///   }
-/// }
-/// ```
+/// - Fetches a **temporary WebSocket token** from [`FeedConfig::token_url`].
+/// - Connects to the **WebSocket endpoint** returned alongside the token, through
+///   `config.socks5_proxy` when set.
+/// - Sends a subscription request for each of [`FeedConfig::topics`]: the depth topic (e.g.
+///   `/contractMarket/level2Depth5:ETHUSDTM`), and the execution topic when `config.track_trades`
+///   is set. Updates are only processed once the server acknowledges the book subscription.
+/// - Routes each message to the book or the trade tracker by its topic, see [`Channel`].
+/// - Once no update was applied for `config.settle_after`, e.g. after a burst, counts a
+///   [`ListenerStats::settled_events`] and passes a snapshot to `config.on_settled`.
+/// - Logs system and status frames, such as maintenance notices, and passes them to `config.on_status`.
+/// - Listens for **real-time bid/ask updates** and updates the `OrderBook` accordingly.
+/// - Before the token expires (see `config.token_refresh`), opens a new connection with a fresh
+///   token and switches to it, counting [`ListenerStats::token_refreshes`].
+/// - With `config.coalesce_window` set, buffers book updates and applies only the latest one per window.
+/// - While `config.pause` is paused, keeps reading and pinging but discards every message.
+/// - Rejects messages larger than `config.max_message_size` by dropping the connection, counting
+///   them in [`ListenerStats::oversized_messages`].
+/// - With `config.resnapshot_on_connect`, fetches a REST snapshot after each (re)connect while
+///   buffering book updates, then replays the buffered updates newer than the snapshot.
+/// - Counts frames that are not valid JSON in [`ListenerStats::parse_failures`], and reconnects
+///   after `config.max_parse_failures` consecutive ones.
+/// - Emits snapshots with `try_send`, so a slow consumer never stalls the read loop.
+///   Stale snapshots are dropped and counted in [`ListenerStats::snapshots_dropped`].
 ///
 /// # Example Usage
 ///
-/// ```rust
-/// let json_data: serde_json::Value = serde_json::from_str(your_json_string).unwrap();
-/// let mut order_book = OrderBook::new();
-/// update_order_book(&mut order_book, json_data);
+/// ```no_run
+/// # use warmup_project::config::FeedConfig;
+/// # use warmup_project::order_book::OrderBook;
+/// # use warmup_project::api::start_websocket_listener;
+/// # async fn run() {
+/// let config = FeedConfig::default();
+/// let mut order_book = OrderBook::with_config(&config);
+/// start_websocket_listener(&mut order_book, &config, None, None).await.unwrap();
+/// # }
 /// ```
 ///
 /// # Notes
 ///
-/// - If a bid or ask **cannot be parsed**, it defaults to `0.0` for price and `0` for size.
-/// - This function only **takes the top 5 levels** from the order book update.
+/// - This function **runs indefinitely** and should be executed in an async runtime.
+/// - Once `config.reconnect.max_attempts` consecutive reconnects fail, the function **exits**.
+/// - WebSocket tokens are **short-lived**, so every reconnect requests a new token.
 ///
 /// # See Also
 ///
-/// - [`OrderBook::update`] - Method that applies the parsed bid/ask data.
-/// - [`OrderBook::print`] - Displays the updated order book.
-fn update_order_book(ob: &mut OrderBook, json_data: Value) {
-    let mut bids = vec![];
-    let mut asks = vec![];
+/// - [`OrderBook::apply_json`] - Processes order book updates received via WebSocket.
+pub async fn start_websocket_listener(
+    ob: &mut OrderBook,
+    config: &FeedConfig,
+    client: Option<Client>,
+    sink: Option<SnapshotSender>,
+) -> Result<ListenerStats, FeedError> {
+    let client = client.unwrap_or_default();
+    let mut stats = ListenerStats::new();
+    let mut attempts = 0;
 
-    // Parse initial bids & asks
-    if let Some(bid_array) = json_data["data"]["bids"].as_array() {
-        for bid in bid_array.iter().take(5) {
-            let price = bid[0].as_f64().unwrap_or(bid[0].as_str()
-            .and_then(|s| s.parse::<f64>().ok()) // Try parsing it
-            .unwrap_or(0.0));
-            let size = bid[1].as_i64().unwrap_or(bid[1].as_str()
-            .and_then(|s| s.parse::<i64>().ok()) // Try parsing it
-            .unwrap_or(0));
-            bids.push((price, size));
-        }
-    }
+    loop {
+        let applied_before = stats.updates_applied;
+        let result = listen_once(ob, config, &client, &mut stats, sink.as_ref(), None).await;
 
-    if let Some(ask_array) = json_data["data"]["asks"].as_array() {
-        for ask in ask_array.iter().take(5) {
-            let price = ask[0].as_f64().unwrap_or(ask[0].as_str()
-            .and_then(|s| s.parse::<f64>().ok()) // Try parsing it
-            .unwrap_or(0.0));
-            let size = ask[1].as_i64().unwrap_or(ask[1].as_str()
-            .and_then(|s| s.parse::<i64>().ok()) // Try parsing it
-            .unwrap_or(0));
-            asks.push((price, size));
+        if let Err(err) = &result {
+            log::error!("❌ Listener Error: {}", err);
+        }
+        if stats.updates_applied > applied_before {
+            attempts = 0;
+        }
+        if attempts >= config.reconnect.max_attempts {
+            return match result {
+                Ok(()) => Ok(stats),
+                Err(err) if attempts == 0 => Err(err),
+                Err(err) => Err(FeedError::ReconnectExhausted { attempts, last: Box::new(err) }),
+            };
         }
-    }
 
-    ob.update(bids, asks);
-    ob.print();
+        attempts += 1;
+        telemetry::record_reconnect(ob);
+        log::warn!("🔄 Reconnecting ({}/{})", attempts, config.reconnect.max_attempts);
+        tokio::time::sleep(config.reconnect.delay).await;
+    }
 }
 
-/// Establishes a WebSocket connection to the KuCoin Futures API and listens for real-time order book updates.
+/// Listens to the order book feed of `exchange` for `config.symbol`, applying every update to `ob`.
 ///
-/// This function:
-/// - Requests a **WebSocket token** from the KuCoin API.
-/// - Extracts the **WebSocket URL** and establishes a **secure connection**.
-/// - **Subscribes to order book updates** for `ETHUSDTM`.
-/// - **Processes and applies market updates** to the provided `OrderBook`.
+/// Connects to [`Exchange::ws_url`], sends [`Exchange::subscribe_message`] and applies each frame
+/// with [`Exchange::apply_message`], pinging every [`Exchange::ping_interval`]. Applied updates
+/// are counted, printed and emitted to `sink` as in [`start_websocket_listener`], and the
+/// connection is reopened as allowed by `config.reconnect`.
 ///
-/// # Arguments
-///
-/// * `ob` - A mutable reference to an `OrderBook` instance that will be updated in real time.
-///
-/// # Returns
+/// Only the venue-neutral parts of `config` apply: the symbol, output, pause, socks5 proxy and
+/// message size limits. KuCoin's subscription acks, token refresh, coalescing and REST
+/// resnapshots need [`start_websocket_listener`].
 ///
-/// Returns `Ok(())` on success, or an error wrapped in `Box<dyn Error>` if any failure occurs.
+/// # Example Usage
 ///
-/// # Behavior
+/// ```no_run
+/// # use warmup_project::api::start_exchange_listener;
+/// # use warmup_project::config::{FeedConfig, Market};
+/// # use warmup_project::exchange::Bybit;
+/// # use warmup_project::order_book::OrderBook;
+/// # async fn run() {
+/// let config = FeedConfig::new("ETHUSDT".parse().unwrap(), Market::Futures);
+/// let mut order_book = OrderBook::with_config(&config);
+/// start_exchange_listener(&mut order_book, &Bybit::default(), &config, None, None).await.unwrap();
+/// # }
+/// ```
+pub async fn start_exchange_listener(
+    ob: &mut OrderBook,
+    exchange: &dyn Exchange,
+    config: &FeedConfig,
+    client: Option<Client>,
+    sink: Option<SnapshotSender>,
+) -> Result<ListenerStats, FeedError> {
+    let client = client.unwrap_or_default();
+    let mut stats = ListenerStats::new();
+    let mut attempts = 0;
+
+    loop {
+        let applied_before = stats.updates_applied;
+        let result = listen_exchange(ob, exchange, config, &client, &mut stats, sink.as_ref()).await;
+
+        if let Err(err) = &result {
+            log::error!("❌ {} Listener Error: {}", exchange.name(), err);
+        }
+        if stats.updates_applied > applied_before {
+            attempts = 0;
+        }
+        if attempts >= config.reconnect.max_attempts {
+            return match result {
+                Ok(()) => Ok(stats),
+                Err(err) if attempts == 0 => Err(err),
+                Err(err) => Err(FeedError::ReconnectExhausted { attempts, last: Box::new(err) }),
+            };
+        }
+
+        attempts += 1;
+        telemetry::record_reconnect(ob);
+        log::warn!("🔄 Reconnecting to {} ({}/{})", exchange.name(), attempts, config.reconnect.max_attempts);
+        tokio::time::sleep(config.reconnect.delay).await;
+    }
+}
+
+/// Connects, subscribes and returns snapshots of the first `n` updates applied to `symbol`'s futures book.
 ///
-/// - Fetches a **temporary WebSocket token** from `https://api-futures.kucoin.com/api/v1/bullet-public`.
-/// - Connects to the **KuCoin Futures WebSocket endpoint** (`wss://ws-api-futures.kucoin.com/`).
-/// - Sends a subscription request for the **top 5 levels** of the ETHUSDTM order book (`/contractMarket/level2Depth5:ETHUSDTM`).
-/// - Listens for **real-time bid/ask updates** and updates the `OrderBook` accordingly.
+/// Nothing is printed. The connection is closed once `n` updates have been collected. An invalid
+/// `symbol` is rejected with [`FeedError::InvalidSymbol`] before connecting. Use
+/// [`collect_updates_with`] to choose the market, endpoints or HTTP client.
 ///
 /// # Example Usage
 ///
-/// ```rust
-/// let mut order_book = OrderBook::new();
-/// start_websocket_listener(&mut order_book).await.unwrap();
+/// ```no_run
+/// # use warmup_project::api::collect_updates;
+/// # async fn run() {
+/// let updates = collect_updates("ETHUSDTM", 3).await.unwrap();
+/// assert_eq!(updates.len(), 3);
+/// # }
 /// ```
+pub async fn collect_updates(symbol: &str, n: usize) -> Result<Vec<OrderBookSnapshot>, FeedError> {
+    let config = FeedConfig {
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::new(Symbol::new(symbol)?, Market::Futures)
+    };
+    collect_updates_with(&config, None, n).await
+}
+
+/// Like [`collect_updates`], for the feed described by `config`.
 ///
-/// # Notes
+/// Runs a single session without reconnecting, so fewer than `n` snapshots are returned if the
+/// server closes the connection first.
+pub async fn collect_updates_with(
+    config: &FeedConfig,
+    client: Option<Client>,
+    n: usize,
+) -> Result<Vec<OrderBookSnapshot>, FeedError> {
+    if n == 0 {
+        return Ok(vec![]);
+    }
+
+    let mut ob = OrderBook::with_config(config);
+    let mut stats = ListenerStats::new();
+    let (sender, mut receiver) = snapshot_channel(n);
+
+    let client = client.unwrap_or_default();
+    listen_once(&mut ob, config, &client, &mut stats, Some(&sender), Some(n as u64)).await?;
+    drop(sender);
+
+    let mut updates = Vec::with_capacity(n);
+    while let Some(snapshot) = receiver.recv().await {
+        updates.push(snapshot);
+    }
+    Ok(updates)
+}
+
+/// Snapshots buffered between the listener task and a [`top_of_book_stream`] before the oldest are dropped.
+const TOP_OF_BOOK_BUFFER: usize = 64;
+
+/// Streams the best bid and ask of `symbol`'s futures book, yielding only when the touch changes.
 ///
-/// - This function **runs indefinitely** and should be executed in an async runtime.
-/// - If the **WebSocket connection is lost**, the function **exits**, and you may need to restart it.
-/// - WebSocket tokens are **short-lived**, so reconnecting requires requesting a new token.
+/// Updates that leave the best bid and ask (prices and sizes) as they were are filtered out, so a
+/// BBO-only consumer is not woken for changes deeper in the book. An invalid `symbol` is rejected
+/// with [`FeedError::InvalidSymbol`]. Use [`top_of_book_stream_with`] to choose the market,
+/// endpoints or HTTP client.
 ///
-/// # See Also
+/// # Example Usage
+///
+/// ```no_run
+/// # use futures_util::StreamExt;
+/// # use warmup_project::api::top_of_book_stream;
+/// # async fn run() {
+/// let mut tops = Box::pin(top_of_book_stream("ETHUSDTM").unwrap());
+/// while let Some(top) = tops.next().await {
+///     println!("{:?} / {:?}", top.best_bid, top.best_ask);
+/// }
+/// # }
+/// ```
+pub fn top_of_book_stream(symbol: &str) -> Result<impl Stream<Item = TopOfBookChange>, FeedError> {
+    let config = FeedConfig {
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::new(Symbol::new(symbol)?, Market::Futures)
+    };
+    Ok(top_of_book_stream_with(config, None))
+}
+
+/// Like [`top_of_book_stream`], for the feed described by `config`.
+///
+/// The feed runs on a spawned task with [`start_websocket_listener`], reconnecting as allowed by
+/// `config.reconnect`, and must be called within a Tokio runtime. The stream ends once the
+/// listener gives up, and dropping the stream stops the listener.
+pub fn top_of_book_stream_with(config: FeedConfig, client: Option<Client>) -> impl Stream<Item = TopOfBookChange> {
+    let (sender, receiver) = snapshot_channel(TOP_OF_BOOK_BUFFER);
+    let listener = AbortOnDrop(tokio::spawn(async move {
+        let mut ob = OrderBook::with_config(&config);
+        if let Err(err) = start_websocket_listener(&mut ob, &config, client, Some(sender)).await {
+            log::error!("❌ Top of book stream ended: {}", err);
+        }
+    }));
+
+    futures_util::stream::unfold((receiver, None, listener), |(mut receiver, mut last, listener)| async move {
+        loop {
+            let top = TopOfBookChange::of(&receiver.recv().await?);
+            if last.as_ref().is_some_and(|last: &TopOfBookChange| last.same_touch(&top)) {
+                continue;
+            }
+            last = Some(top.clone());
+            return Some((top, (receiver, last, listener)));
+        }
+    })
+}
+
+/// Aborts a spawned task when dropped.
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// The two halves of an open WebSocket connection.
+type WsWrite = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+type WsRead = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+
+/// Fetches a token, connects and subscribes to the topics of `config`.
 ///
-/// - [`update_order_book`] - Processes order book updates received via WebSocket.
-pub async fn start_websocket_listener(ob: &mut OrderBook) -> Result<(), Box<dyn Error>> {
-    let client = Client::new();
-    let ws_token_url = "https://api-futures.kucoin.com/api/v1/bullet-public";
+/// With the `tracing` feature, each of the three steps runs in its own span (`token_fetch`,
+/// `connect` and `subscribe`) carrying the symbol.
+async fn open_session(
+    config: &FeedConfig,
+    client: &Client,
+) -> Result<(WsWrite, WsRead, BulletInfo), FeedError> {
+    let bullet = fetch_bullet(config, client).await?;
+    let ws_stream = connect(config, &bullet).await?;
+    let (mut write, mut read) = ws_stream.split();
+    subscribe(config, &bullet, &mut write, &mut read).await?;
+    Ok((write, read, bullet))
+}
 
+/// Requests a WebSocket token and the endpoint to connect to.
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "token_fetch", skip_all, fields(symbol = %config.symbol)))]
+pub(crate) async fn fetch_bullet(config: &FeedConfig, client: &Client) -> Result<BulletInfo, FeedError> {
     // 1️⃣ Fetch WebSocket token
-    let response = client.post(ws_token_url).send().await?;
-    let response_text = response.text().await?;
-    let json_data: Value = serde_json::from_str(&response_text)?;
+    let response_text = client
+        .post(config.token_url())
+        .send()
+        .await
+        .map_err(|err| FeedError::TokenFetch(err.to_string()))?
+        .text()
+        .await
+        .map_err(|err| FeedError::TokenFetch(err.to_string()))?;
 
     // 2️⃣ Extract WebSocket URL & Token
-    let ws_url = json_data["data"]["instanceServers"][0]["endpoint"]
-        .as_str()
-        .ok_or("WebSocket URL not found")?;
-    
-    let token = json_data["data"]["token"]
-        .as_str()
-        .ok_or("WebSocket Token not found")?;
+    parse_bullet(&response_text)
+}
+
+/// Opens the WebSocket connection to the endpoint of `bullet`.
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "connect", skip_all, fields(symbol = %config.symbol)))]
+async fn connect(
+    config: &FeedConfig,
+    bullet: &BulletInfo,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, FeedError> {
+    let full_ws_url = format!("{}?token={}", bullet.endpoint, bullet.token); // ✅ Include token in WebSocket URL
+    connect_url(config, &full_ws_url).await
+}
 
-    let full_ws_url = format!("{}?token={}", ws_url, token); // ✅ Include token in WebSocket URL
-    
-    println!("Connecting to WebSocket: {}", full_ws_url);
+/// Opens the WebSocket connection to `full_ws_url`, through `config.socks5_proxy` when set.
+async fn connect_url(
+    config: &FeedConfig,
+    full_ws_url: &str,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, FeedError> {
+    log::info!("🔌 Connecting to WebSocket: {}", full_ws_url);
 
-    // 3️⃣ Connect to KuCoin WebSocket
-    let (ws_stream, _) = connect_async(full_ws_url).await.expect("Failed to connect to WebSocket");
-    println!("✅ Connected to KuCoin WebSocket");
+    // 3️⃣ Connect to KuCoin WebSocket, refusing oversized messages rather than buffering them
+    let ws_config = WebSocketConfig {
+        max_message_size: config.max_message_size,
+        max_frame_size: config.max_frame_size,
+        ..WebSocketConfig::default()
+    };
+    let ws_stream = match &config.socks5_proxy {
+        Some(proxy) => connect_via_socks5(full_ws_url, proxy, ws_config).await?,
+        None => {
+            connect_async_with_config(full_ws_url, Some(ws_config), false)
+                .await
+                .map_err(|err| FeedError::WsConnect(err.to_string()))?
+                .0
+        }
+    };
+    log::info!("✅ Connected to WebSocket");
+    Ok(ws_stream)
+}
 
-    let (mut write, mut read) = ws_stream.split();
+/// Subscribes to the topics of `config` and waits for every subscription to be acknowledged.
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "subscribe", skip_all, fields(symbol = %config.symbol)))]
+async fn subscribe(
+    config: &FeedConfig,
+    bullet: &BulletInfo,
+    write: &mut WsWrite,
+    read: &mut WsRead,
+) -> Result<(), FeedError> {
+    // 4️⃣ Subscribe to the order book and any other topics over the same connection
+    let subscription_ids = subscribe_topics(write, &config.topics(), config.private_channel).await?;
+
+    // ✅ Only start processing updates once every subscription is confirmed
+    wait_for_acks(read, &subscription_ids, bullet.ping_timeout).await?;
+    for subscription_id in &subscription_ids {
+        log::info!("🔹 Subscription {} acknowledged", subscription_id);
+        #[cfg(feature = "tracing")]
+        tracing::info!(id = %subscription_id, "subscription acknowledged");
+    }
+    Ok(())
+}
+
+/// Runs a single WebSocket session until the connection is closed or fails.
+///
+/// With `stop_after`, the session is closed by the client once that many updates have been applied.
+/// When `config.token_refresh` elapses, a new session is opened with a fresh token and swapped in
+/// before the old connection is closed, so the book keeps updating across the refresh. A book that
+/// stays crossed for `config.resync_on_crossed` updates is reloaded from a REST snapshot and the
+/// session is swapped the same way.
+async fn listen_once(
+    ob: &mut OrderBook,
+    config: &FeedConfig,
+    client: &Client,
+    stats: &mut ListenerStats,
+    sink: Option<&SnapshotSender>,
+    stop_after: Option<u64>,
+) -> Result<(), FeedError> {
+    let (mut write, mut read, mut bullet) = open_session(config, client).await?;
+    stats.connected_at = Instant::now();
+    if config.resnapshot_on_connect {
+        resnapshot(ob, config, client, &mut read, stats, sink).await;
+    }
+
+    // 5️⃣ Listen for updates, pinging at the server's interval
+    let mut keepalive = tokio::time::interval(bullet.ping_interval);
+    keepalive.tick().await;
+    let mut last_seen = Instant::now();
+
+    // Swap in a fresh token before the current one expires
+    let refresh_after = config.token_refresh.unwrap_or(Duration::MAX);
+    let mut token_issued = Instant::now();
+
+    // With a coalescing window, only the latest book update of each window is applied
+    let mut flush = tokio::time::interval(config.coalesce_window.unwrap_or(bullet.ping_interval));
+    flush.tick().await;
+    let mut pending: Option<Value> = None;
+
+    // The book settles once no update was applied for `settle_after`; each update resets the timer
+    let mut settle_at = tokio::time::Instant::now();
+    let mut unsettled = false;
+    let mut applied_seen = stats.updates_applied;
+
+    loop {
+        // A book that stays crossed has most likely missed updates, start over from a fresh snapshot
+        if config.resync_on_crossed.is_some_and(|limit| stats.consecutive_crossed >= limit) {
+            log::warn!(
+                "⚠️ Book crossed for {} consecutive updates, resnapshotting and resubscribing",
+                stats.consecutive_crossed
+            );
+            stats.consecutive_crossed = 0;
+            let (new_write, new_read, new_bullet) = open_session(config, client).await?;
+            let _ = write.send(Message::Close(None)).await;
+            (write, read, bullet) = (new_write, new_read, new_bullet);
+            resnapshot(ob, config, client, &mut read, stats, sink).await;
+            keepalive = tokio::time::interval(bullet.ping_interval);
+            keepalive.tick().await;
+            last_seen = Instant::now();
+            stats.crossed_resyncs += 1;
+        }
+
+        if stats.updates_applied > applied_seen {
+            applied_seen = stats.updates_applied;
+            if let Some(settle_after) = config.settle_after {
+                settle_at = tokio::time::Instant::now() + settle_after;
+                unsettled = true;
+            }
+        }
+
+        tokio::select! {
+            msg = read.next() => {
+                let Some(msg) = msg else { break };
+                last_seen = Instant::now();
+
+                match msg {
+                    Ok(Message::Text(text)) => {
+                        if config.print.raw_messages {
+                            log::info!("📩 WebSocket Message: {}", text); // ✅ Debugging Output
+                        }
+                        match book_update(&text) {
+                            Some(update) if config.coalesce_window.is_some() && !config.pause.is_paused() => {
+                                stats.messages_received += 1;
+                                retain_message(config, stats, &text);
+                                stats.consecutive_parse_failures = 0;
+                                telemetry::record_message(ob);
+                                pending = Some(update);
+                            }
+                            _ => handle_text(ob, &text, config, stats, sink),
+                        }
+                        if config.max_parse_failures.is_some_and(|limit| stats.consecutive_parse_failures >= limit) {
+                            let failures = std::mem::take(&mut stats.consecutive_parse_failures);
+                            let _ = write.send(Message::Close(None)).await;
+                            return Err(FeedError::Parse(format!(
+                                "{} consecutive malformed messages, assuming the stream is out of sync",
+                                failures
+                            )));
+                        }
+                        if stop_after.is_some_and(|limit| stats.updates_applied >= limit) {
+                            let _ = write.send(Message::Close(None)).await;
+                            break;
+                        }
+                    }
+                    Ok(Message::Close(_)) => {
+                        log::warn!("❌ WebSocket Closed by Server.");
+                        break;
+                    }
+                    Err(WsError::Capacity(err)) => {
+                        log::error!("❌ Oversized WebSocket message rejected: {}", err);
+                        stats.oversized_messages += 1;
+                        let _ = write.send(Message::Close(None)).await;
+                        break;
+                    }
+                    Err(err) => {
+                        log::error!("❌ WebSocket Error: {}", err);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            _ = flush.tick(), if config.coalesce_window.is_some() => {
+                if let Some(update) = pending.take().filter(|_| !config.pause.is_paused()) {
+                    apply_update(ob, &update, config, stats, sink);
+                    if stop_after.is_some_and(|limit| stats.updates_applied >= limit) {
+                        let _ = write.send(Message::Close(None)).await;
+                        break;
+                    }
+                }
+            }
+            _ = tokio::time::sleep(refresh_after.saturating_sub(token_issued.elapsed())), if config.token_refresh.is_some() => {
+                token_issued = Instant::now();
+                match open_session(config, client).await {
+                    Ok((new_write, new_read, new_bullet)) => {
+                        log::info!("🔑 Token refreshed, switching to the new connection");
+                        let _ = write.send(Message::Close(None)).await;
+                        (write, read, bullet) = (new_write, new_read, new_bullet);
+                        keepalive = tokio::time::interval(bullet.ping_interval);
+                        keepalive.tick().await;
+                        last_seen = Instant::now();
+                        stats.token_refreshes += 1;
+                    }
+                    // Keep the current connection, it is dropped and reconnected once the token expires
+                    Err(err) => log::warn!("⚠️ Token refresh failed: {}", err),
+                }
+            }
+            _ = tokio::time::sleep_until(settle_at), if unsettled => {
+                unsettled = false;
+                stats.settled_events += 1;
+                if let Some(handler) = &config.on_settled {
+                    handler.call(&ob.snapshot());
+                }
+            }
+            _ = keepalive.tick() => {
+                if last_seen.elapsed() > bullet.ping_interval + bullet.ping_timeout {
+                    log::warn!("❌ No message within the ping timeout, dropping connection.");
+                    break;
+                }
+                if write.send(Message::Text(ping_message())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
 
-    // 4️⃣ Subscribe to order book updates
-    let subscription_msg = serde_json::json!({
-        "id": "1",
+/// Runs a single session of [`start_exchange_listener`], returning once the connection is lost.
+async fn listen_exchange(
+    ob: &mut OrderBook,
+    exchange: &dyn Exchange,
+    config: &FeedConfig,
+    client: &Client,
+    stats: &mut ListenerStats,
+    sink: Option<&SnapshotSender>,
+) -> Result<(), FeedError> {
+    let full_ws_url = exchange.ws_url(config, client).await?;
+    let (mut write, mut read) = connect_url(config, &full_ws_url).await?.split();
+    write
+        .send(Message::Text(exchange.subscribe_message(&config.symbol)))
+        .await
+        .map_err(|err| FeedError::Subscribe(err.to_string()))?;
+    stats.connected_at = Instant::now();
+
+    let mut keepalive = tokio::time::interval(exchange.ping_interval());
+    keepalive.tick().await;
+
+    loop {
+        tokio::select! {
+            msg = read.next() => {
+                let Some(msg) = msg else { break };
+
+                match msg {
+                    Ok(Message::Text(text)) => {
+                        if config.print.raw_messages {
+                            log::info!("📩 WebSocket Message: {}", text);
+                        }
+                        stats.messages_received += 1;
+                        telemetry::record_message(ob);
+                        if config.pause.is_paused() {
+                            continue;
+                        }
+                        retain_message(config, stats, &text);
+
+                        let json_data = match serde_json::from_str::<Value>(&text) {
+                            Ok(json_data) => json_data,
+                            Err(err) => {
+                                log::warn!("⚠️ Malformed message: {}", err);
+                                stats.parse_failures += 1;
+                                stats.consecutive_parse_failures += 1;
+                                continue;
+                            }
+                        };
+                        stats.consecutive_parse_failures = 0;
+                        if let Some(report) = exchange.apply_message(ob, &json_data) {
+                            record_update(ob, &report, config, stats, sink);
+                        }
+                    }
+                    Ok(Message::Close(_)) => {
+                        log::warn!("❌ WebSocket Closed by Server.");
+                        break;
+                    }
+                    Err(WsError::Capacity(err)) => {
+                        log::error!("❌ Oversized WebSocket message rejected: {}", err);
+                        stats.oversized_messages += 1;
+                        let _ = write.send(Message::Close(None)).await;
+                        break;
+                    }
+                    Err(err) => {
+                        log::error!("❌ WebSocket Error: {}", err);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            _ = keepalive.tick() => {
+                if write.send(Message::Text(exchange.ping_message())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Fetches a REST snapshot while buffering the book updates arriving on `read`, then applies
+/// both with [`OrderBook::resync`].
+///
+/// Other frames, such as trades, are handled as usual in the meantime. If the snapshot cannot be
+/// fetched, the buffered updates are applied on top of the current book instead. Either way the
+/// resulting book is printed and emitted once. Replayed updates are only counted in
+/// `updates_replayed`, so they do not count towards a `stop_after` limit.
+async fn resnapshot(
+    ob: &mut OrderBook,
+    config: &FeedConfig,
+    client: &Client,
+    read: &mut WsRead,
+    stats: &mut ListenerStats,
+    sink: Option<&SnapshotSender>,
+) {
+    let snapshot = fetch_snapshot(config, Some(client.clone()));
+    tokio::pin!(snapshot);
+    let mut buffered = vec![];
+
+    let snapshot = loop {
+        tokio::select! {
+            snapshot = &mut snapshot => break snapshot,
+            msg = read.next() => match msg {
+                Some(Ok(Message::Text(text))) => match book_update(&text) {
+                    Some(update) => {
+                        stats.messages_received += 1;
+                        retain_message(config, stats, &text);
+                        buffered.push(update);
+                    }
+                    None => handle_text(ob, &text, config, stats, sink),
+                },
+                Some(Ok(_)) => {}
+                // The read loop notices the lost connection, only the snapshot is awaited here
+                Some(Err(_)) | None => break (&mut snapshot).await,
+            },
+        }
+    };
+
+    let replayed = match snapshot {
+        Ok(snapshot) => {
+            let replayed = ob.resync(&snapshot, &buffered);
+            log::info!(
+                "📸 Resnapshotted at sequence {:?}, replayed {} of {} buffered updates",
+                snapshot.sequence, replayed, buffered.len()
+            );
+            replayed
+        }
+        Err(err) => {
+            log::warn!("⚠️ Resnapshot failed, applying {} buffered updates as they are: {}", buffered.len(), err);
+            ob.apply_batch(&buffered);
+            buffered.len()
+        }
+    };
+    stats.updates_replayed += replayed as u64;
+    publish(ob, config, stats, sink);
+}
+
+static NEXT_MESSAGE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Returns a message id unique to this process.
+fn next_message_id() -> String {
+    NEXT_MESSAGE_ID.fetch_add(1, Ordering::Relaxed).to_string()
+}
+
+/// Builds a KuCoin keepalive `ping` frame.
+pub fn ping_message() -> String {
+    serde_json::json!({
+        "id": next_message_id(),
+        "type": "ping"
+    })
+    .to_string()
+}
+
+/// Builds a subscribe request for `topic` with an id unique to this process.
+///
+/// Returns the id alongside the serialized message, so the matching `ack` can be recognized.
+pub fn subscribe_message(topic: &str) -> (String, String) {
+    subscribe_request(topic, false)
+}
+
+/// Like [`subscribe_message`], setting `privateChannel` for private feeds such as order updates.
+///
+/// `response` is always set, so the server confirms the subscription with an `ack`.
+pub fn subscribe_request(topic: &str, private_channel: bool) -> (String, String) {
+    let id = next_message_id();
+    let msg = serde_json::json!({
+        "id": id,
         "type": "subscribe",
-        "topic": "/contractMarket/level2Depth5:ETHUSDTM",
+        "topic": topic,
+        "privateChannel": private_channel,
         "response": true
     })
     .to_string();
+    (id, msg)
+}
 
-    write.send(Message::Text(subscription_msg)).await.expect("Failed to send subscription message");
+/// Connects to `url` through the SOCKS5 `proxy`, then performs the TLS (for `wss`) and WebSocket handshakes.
+async fn connect_via_socks5(
+    url: &str,
+    proxy: &str,
+    ws_config: WebSocketConfig,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, FeedError> {
+    let request = url
+        .into_client_request()
+        .map_err(|err| FeedError::WsConnect(err.to_string()))?;
+    let uri = request.uri();
+    let host = uri
+        .host()
+        .ok_or_else(|| FeedError::WsConnect(format!("no host in {}", url)))?
+        .to_string();
+    let port = uri
+        .port_u16()
+        .unwrap_or(if uri.scheme_str() == Some("wss") { 443 } else { 80 });
 
-    // ✅ Confirm subscription response
-    if let Some(msg) = read.next().await {
-        if let Ok(Message::Text(text)) = msg {
-            println!("🔹 Subscription Response: {}", text);
-        }
+    log::info!("🧦 Tunneling through SOCKS5 proxy {}", proxy);
+    let stream = socks5_connect(proxy, &host, port)
+        .await
+        .map_err(|err| FeedError::WsConnect(err.to_string()))?;
+    let (ws_stream, _) = client_async_tls_with_config(request, stream, Some(ws_config), None)
+        .await
+        .map_err(|err| FeedError::WsConnect(err.to_string()))?;
+    Ok(ws_stream)
+}
+
+/// Sends a subscribe request for each of `topics`, returning their ids in the same order.
+async fn subscribe_topics(
+    write: &mut WsWrite,
+    topics: &[String],
+    private_channel: bool,
+) -> Result<Vec<String>, FeedError> {
+    let mut ids = Vec::with_capacity(topics.len());
+    for topic in topics {
+        let (id, msg) = subscribe_request(topic, private_channel);
+        write
+            .send(Message::Text(msg))
+            .await
+            .map_err(|err| FeedError::Subscribe(err.to_string()))?;
+        ids.push(id);
     }
+    Ok(ids)
+}
 
-    // 5️⃣ Listen for updates
-    while let Some(msg) = read.next().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                println!("📩 WebSocket Message: {}", text); // ✅ Debugging Output
-                
-                if let Ok(json_data) = serde_json::from_str::<Value>(&text) {
-                    if json_data["type"] == "message" {
-                        update_order_book(ob, json_data);
-                    }
+/// Reads frames until the `ack` of every subscription in `ids` arrives, giving up after `timeout`.
+///
+/// Frames before the acks, such as the `welcome` message or updates racing the acks, are skipped.
+/// An `error` frame for any of the subscriptions is returned as [`FeedError::Subscribe`].
+async fn wait_for_acks(read: &mut WsRead, ids: &[String], timeout: Duration) -> Result<(), FeedError> {
+    let mut pending: Vec<&str> = ids.iter().map(String::as_str).collect();
+    let wait = async {
+        while !pending.is_empty() {
+            let text = match read.next().await {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(_)) => continue,
+                Some(Err(err)) => return Err(FeedError::Subscribe(err.to_string())),
+                None => {
+                    return Err(FeedError::Subscribe("connection closed before the subscription ack".to_string()))
                 }
+            };
+
+            if let Some(index) = pending.iter().position(|id| is_ack_for(&text, id)) {
+                pending.swap_remove(index);
+                continue;
             }
-            Ok(Message::Close(_)) => {
-                eprintln!("❌ WebSocket Closed by Server.");
-                break;
+            let json_data: Value = serde_json::from_str(&text).unwrap_or_default();
+            if json_data["type"] == "error" && ids.iter().any(|id| json_data["id"] == id.as_str()) {
+                return Err(FeedError::Subscribe(text));
             }
-            Err(err) => {
-                eprintln!("❌ WebSocket Error: {}", err);
-                break;
+            log::debug!("🔹 Skipping frame before subscription ack: {}", text);
+        }
+        Ok(())
+    };
+
+    match tokio::time::timeout(timeout, wait).await {
+        Ok(result) => result,
+        Err(_) => Err(FeedError::Subscribe(format!(
+            "no ack for subscriptions {:?} within {:?}",
+            pending, timeout
+        ))),
+    }
+}
+
+/// Returns `true` if `text` is the `ack` frame for the subscription with the given id.
+pub fn is_ack_for(text: &str, id: &str) -> bool {
+    match serde_json::from_str::<Value>(text) {
+        Ok(json_data) => json_data["type"] == "ack" && json_data["id"] == id,
+        Err(_) => false,
+    }
+}
+
+/// The WebSocket endpoint and token handed out by the bullet-public endpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulletInfo {
+    pub endpoint: String,
+    pub token: String,
+    /// How often the server expects a `ping`.
+    pub ping_interval: Duration,
+    /// How long the server waits for a `ping` before closing the connection.
+    pub ping_timeout: Duration,
+}
+
+/// Keepalive timing used when the bullet response omits `pingInterval`/`pingTimeout`.
+const DEFAULT_PING_INTERVAL: Duration = Duration::from_millis(18000);
+const DEFAULT_PING_TIMEOUT: Duration = Duration::from_millis(10000);
+
+/// Parses the body of a bullet-public response.
+///
+/// The keepalive timing is read from `pingInterval` and `pingTimeout` (in milliseconds) of the
+/// first instance server. Returns [`FeedError::TokenFetch`] if the body is not JSON, offers no
+/// instance server, or lacks the endpoint or token.
+pub fn parse_bullet(response_text: &str) -> Result<BulletInfo, FeedError> {
+    let json_data: Value = serde_json::from_str(response_text)
+        .map_err(|err| FeedError::TokenFetch(format!("invalid bullet response: {}", err)))?;
+
+    // An empty list would otherwise surface as a missing URL, hiding that no server was offered
+    let servers = &json_data["data"]["instanceServers"];
+    if servers.as_array().is_some_and(|servers| servers.is_empty()) {
+        return Err(FeedError::TokenFetch(
+            "no WebSocket server available, instanceServers is empty".to_string(),
+        ));
+    }
+
+    let server = &servers[0];
+    let endpoint = server["endpoint"]
+        .as_str()
+        .ok_or_else(|| FeedError::TokenFetch("WebSocket URL not found".to_string()))?;
+
+    let token = json_data["data"]["token"]
+        .as_str()
+        .ok_or_else(|| FeedError::TokenFetch("WebSocket Token not found".to_string()))?;
+
+    let millis = |key: &str, default: Duration| {
+        server[key].as_u64().map_or(default, Duration::from_millis)
+    };
+
+    Ok(BulletInfo {
+        endpoint: endpoint.to_string(),
+        token: token.to_string(),
+        ping_interval: millis("pingInterval", DEFAULT_PING_INTERVAL),
+        ping_timeout: millis("pingTimeout", DEFAULT_PING_TIMEOUT),
+    })
+}
+
+/// Fetches a depth snapshot of `config.symbol` from the REST API.
+///
+/// The snapshot is requested at [`FeedConfig::snapshot_depth`], since the REST API only serves
+/// 20 or 100 levels, and truncated to `config.depth` levels per side, best price first. Pass a `client`
+/// to control proxies, timeouts or certificates; a default client is built when `None`.
+///
+/// # Example Usage
+///
+/// ```no_run
+/// # use warmup_project::config::FeedConfig;
+/// # use warmup_project::api::fetch_snapshot;
+/// # async fn run() {
+/// let snapshot = fetch_snapshot(&FeedConfig::default(), None).await.unwrap();
+/// println!("best bid: {:?}", snapshot.bids.first());
+/// # }
+/// ```
+pub async fn fetch_snapshot(
+    config: &FeedConfig,
+    client: Option<Client>,
+) -> Result<OrderBookSnapshot, FeedError> {
+    let response = client
+        .unwrap_or_default()
+        .get(config.depth_url())
+        .send()
+        .await
+        .map_err(|err| FeedError::Snapshot(err.to_string()))?;
+
+    if !response.status().is_success() {
+        return Err(FeedError::Snapshot(format!("HTTP {}", response.status())));
+    }
+
+    let response_text = response
+        .text()
+        .await
+        .map_err(|err| FeedError::Snapshot(err.to_string()))?;
+    let json_data: Value = serde_json::from_str(&response_text)
+        .map_err(|err| FeedError::Parse(err.to_string()))?;
+
+    let mut ob = OrderBook::with_config(config);
+    ob.apply_json(&json_data);
+    Ok(ob.snapshot())
+}
+
+/// Applies a single text frame to the order book, prints it and emits the resulting snapshot.
+///
+/// With the `tracing` feature, runs in a `message` span carrying the symbol and, for book
+/// updates, the sequence the book reached.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "message", skip_all, fields(symbol = %config.symbol, sequence = tracing::field::Empty))
+)]
+pub(crate) fn handle_text(
+    ob: &mut OrderBook,
+    text: &str,
+    config: &FeedConfig,
+    stats: &mut ListenerStats,
+    sink: Option<&SnapshotSender>,
+) {
+    stats.messages_received += 1;
+    telemetry::record_message(ob);
+
+    // Paused: the frame was read to keep the socket drained, but is not applied
+    if config.pause.is_paused() {
+        return;
+    }
+    retain_message(config, stats, text);
+
+    let json_data = match serde_json::from_str::<Value>(text) {
+        Ok(json_data) => json_data,
+        Err(err) => {
+            log::warn!("⚠️ Malformed message: {}", err);
+            stats.parse_failures += 1;
+            stats.consecutive_parse_failures += 1;
+            return;
+        }
+    };
+    stats.consecutive_parse_failures = 0;
+
+    if let Some(status) = SystemStatus::from_frame(&json_data) {
+        log::warn!("📢 System status, the feed may degrade: {}", status);
+        stats.status_messages += 1;
+        if let Some(handler) = &config.on_status {
+            handler.call(&status);
+        }
+        return;
+    }
+
+    match Channel::of(&json_data) {
+        Some(Channel::Trades) => {
+            ob.apply_execution(&json_data);
+        }
+        Some(Channel::Book) => apply_update(ob, &json_data, config, stats, sink),
+        None => {}
+    }
+}
+
+/// Keeps a copy of `text` in the stats when `config.retain_last_message` is set.
+fn retain_message(config: &FeedConfig, stats: &mut ListenerStats, text: &str) {
+    if config.retain_last_message {
+        stats.last_message = Some(text.to_string());
+    }
+}
+
+/// Parses `text` if it is an order book update, as opposed to an ack, pong or trade.
+fn book_update(text: &str) -> Option<Value> {
+    let json_data = serde_json::from_str::<Value>(text).ok()?;
+    (Channel::of(&json_data) == Some(Channel::Book)).then_some(json_data)
+}
+
+/// Topic prefixes of the depth feeds.
+const BOOK_TOPIC_PREFIXES: [&str; 2] = ["/contractMarket/level2", "/spotMarket/level2"];
+/// Topic prefixes of the trade feeds.
+const TRADE_TOPIC_PREFIXES: [&str; 2] = ["/contractMarket/execution:", "/market/match:"];
+
+/// The state a `message` frame updates, as routed by its topic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// Depth updates, applied to the order book.
+    Book,
+    /// Trade executions, recorded by the trade tracker.
+    Trades,
+}
+
+impl Channel {
+    /// Routes a frame by the prefix of its `topic`.
+    ///
+    /// Returns `None` for frames that are not `message`s, such as acks and pongs, and for unknown
+    /// topics. A `message` without a topic is treated as a book update.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::api::Channel;
+    /// let trade = serde_json::json!({"type": "message", "topic": "/contractMarket/execution:ETHUSDTM"});
+    /// assert_eq!(Channel::of(&trade), Some(Channel::Trades));
+    /// assert_eq!(Channel::of(&serde_json::json!({"type": "pong"})), None);
+    /// ```
+    pub fn of(json_data: &Value) -> Option<Channel> {
+        if json_data["type"] != "message" {
+            return None;
+        }
+        let Some(topic) = json_data["topic"].as_str() else {
+            return Some(Channel::Book);
+        };
+
+        if BOOK_TOPIC_PREFIXES.iter().any(|prefix| topic.starts_with(prefix)) {
+            Some(Channel::Book)
+        } else if TRADE_TOPIC_PREFIXES.iter().any(|prefix| topic.starts_with(prefix)) {
+            Some(Channel::Trades)
+        } else {
+            None
+        }
+    }
+}
+
+/// Applies a parsed order book update, prints the book and emits the resulting snapshot.
+///
+/// Stale updates, whose sequence the book has already applied, leave the book unchanged and are
+/// only counted in `stale_updates`: they are neither printed nor emitted.
+fn apply_update(
+    ob: &mut OrderBook,
+    json_data: &Value,
+    config: &FeedConfig,
+    stats: &mut ListenerStats,
+    sink: Option<&SnapshotSender>,
+) {
+    let report = ob.apply_json(json_data);
+    record_update(ob, &report, config, stats, sink);
+}
+
+/// Counts an update that `report` says was applied to `ob`, then prints the book and emits its snapshot.
+///
+/// Stale updates are only counted in `stale_updates`.
+fn record_update(
+    ob: &OrderBook,
+    report: &UpdateReport,
+    config: &FeedConfig,
+    stats: &mut ListenerStats,
+    sink: Option<&SnapshotSender>,
+) {
+    if report.sequence == Some(SequenceCheck::Stale) {
+        log::debug!("🔹 Skipping a stale update, the book is already at sequence {:?}", ob.last_sequence());
+        stats.stale_updates += 1;
+        return;
+    }
+    stats.updates_applied += 1;
+    match ob.is_crossed() {
+        true => stats.consecutive_crossed += 1,
+        false => stats.consecutive_crossed = 0,
+    }
+    telemetry::record_book(ob);
+    #[cfg(feature = "tracing")]
+    if let Some(sequence) = ob.last_sequence() {
+        tracing::Span::current().record("sequence", sequence);
+    }
+    publish(ob, config, stats, sink);
+}
+
+/// Prints the book as configured and emits its snapshot to `sink`.
+fn publish(ob: &OrderBook, config: &FeedConfig, stats: &mut ListenerStats, sink: Option<&SnapshotSender>) {
+    if config.print.book {
+        match config.print.format {
+            OutputFormat::Table => ob.print(),
+            OutputFormat::Ticker if config.print.in_place => {
+                print!("{}", in_place(&ob.render(OutputFormat::Ticker)));
+                let _ = std::io::Write::flush(&mut std::io::stdout());
             }
-            _ => {}
+            format => print!("{}", ob.render(format)),
         }
     }
 
-    Ok(())
+    if let Some(sink) = sink {
+        stats.snapshots_emitted += 1;
+        if sink.try_send(ob.snapshot()) {
+            stats.snapshots_dropped += 1;
+        }
+    }
+}
+
+/// Fetches the symbols of every tradeable futures contract, e.g. to validate a user-supplied symbol.
+///
+/// Queries [`FeedConfig::contracts_url`] of `config`, so it only applies to futures endpoints.
+/// Pass a `client` to control proxies, timeouts or certificates; a default client is built when `None`.
+///
+/// # Example Usage
+///
+/// ```no_run
+/// # use warmup_project::config::FeedConfig;
+/// # use warmup_project::api::fetch_active_contracts;
+/// # async fn run() {
+/// let symbols = fetch_active_contracts(&FeedConfig::default(), None).await.unwrap();
+/// assert!(symbols.iter().any(|symbol| symbol == "ETHUSDTM"));
+/// # }
+/// ```
+pub async fn fetch_active_contracts(
+    config: &FeedConfig,
+    client: Option<Client>,
+) -> Result<Vec<String>, FeedError> {
+    parse_active_contracts(&fetch_contracts_text(config, client).await?)
+}
+
+/// Fetches the contract multiplier of `config.symbol`, in base-currency units per contract.
+///
+/// Fails with [`FeedError::Contracts`] if the symbol is not an active contract. See
+/// [`FeedConfig::apply_multiplier`] to use the result for base-currency conversions.
+///
+/// # Example Usage
+///
+/// ```no_run
+/// # use warmup_project::config::FeedConfig;
+/// # use warmup_project::api::fetch_contract_multiplier;
+/// # async fn run() {
+/// let mut config = FeedConfig::default();
+/// let multiplier = fetch_contract_multiplier(&config, None).await.unwrap();
+/// config.apply_multiplier(multiplier);
+/// # }
+/// ```
+pub async fn fetch_contract_multiplier(
+    config: &FeedConfig,
+    client: Option<Client>,
+) -> Result<f64, FeedError> {
+    let multipliers = parse_contract_multipliers(&fetch_contracts_text(config, client).await?)?;
+    multipliers
+        .get(config.symbol.as_str())
+        .copied()
+        .ok_or_else(|| FeedError::Contracts(format!("{} is not an active contract", config.symbol)))
+}
+
+/// Downloads the body of the `contracts/active` endpoint of `config`.
+async fn fetch_contracts_text(config: &FeedConfig, client: Option<Client>) -> Result<String, FeedError> {
+    let response = client
+        .unwrap_or_default()
+        .get(config.contracts_url())
+        .send()
+        .await
+        .map_err(|err| FeedError::Contracts(err.to_string()))?;
+
+    if !response.status().is_success() {
+        return Err(FeedError::Contracts(format!("HTTP {}", response.status())));
+    }
+
+    response
+        .text()
+        .await
+        .map_err(|err| FeedError::Contracts(err.to_string()))
+}
+
+/// Parses the body of a `contracts/active` response into the `symbol` of each entry.
+///
+/// Returns [`FeedError::Contracts`] if the body is not JSON or `data` is not a list.
+/// Entries without a `symbol` are skipped.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::api::parse_active_contracts;
+/// let body = r#"{"code":"200000","data":[{"symbol":"XBTUSDTM"},{"symbol":"ETHUSDTM"}]}"#;
+/// assert_eq!(parse_active_contracts(body).unwrap(), vec!["XBTUSDTM", "ETHUSDTM"]);
+/// ```
+pub fn parse_active_contracts(response_text: &str) -> Result<Vec<String>, FeedError> {
+    Ok(contract_entries(response_text)?
+        .iter()
+        .filter_map(|contract| contract["symbol"].as_str())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Parses the body of a `contracts/active` response into each contract's `multiplier`, keyed by symbol.
+///
+/// Entries without a `symbol` or a positive `multiplier` are skipped. Errors as [`parse_active_contracts`].
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::api::parse_contract_multipliers;
+/// let body = r#"{"code":"200000","data":[{"symbol":"XBTUSDTM","multiplier":0.001}]}"#;
+/// assert_eq!(parse_contract_multipliers(body).unwrap()["XBTUSDTM"], 0.001);
+/// ```
+pub fn parse_contract_multipliers(response_text: &str) -> Result<HashMap<String, f64>, FeedError> {
+    Ok(contract_entries(response_text)?
+        .iter()
+        .filter_map(|contract| {
+            let symbol = contract["symbol"].as_str()?;
+            let multiplier = coerce_f64(&contract["multiplier"]).filter(|&multiplier| multiplier > 0.0)?;
+            Some((symbol.to_string(), multiplier))
+        })
+        .collect())
+}
+
+/// The entries of the `data` list of a `contracts/active` response.
+fn contract_entries(response_text: &str) -> Result<Vec<Value>, FeedError> {
+    let mut json_data: Value = serde_json::from_str(response_text)
+        .map_err(|err| FeedError::Contracts(format!("invalid contracts response: {}", err)))?;
+
+    match json_data["data"].take() {
+        Value::Array(contracts) => Ok(contracts),
+        _ => Err(FeedError::Contracts("contract list not found".to_string())),
+    }
 }
@@ -1,12 +1,16 @@
-///Local testing
-///This tests the API and sockets
+//!Local testing
+//!This tests the API and sockets
 //use reqwest::Error;
 use reqwest::Client;
+use std::time::Duration;
 use serde_json::Value;
+use crate::parse::{coerce_f64, coerce_i64};
 // use std::error::Error;
 
 ///Test the call of API once to ensure it is properly connected to the endpoint
-pub async fn api_test_once() -> Result<(), Box<dyn std::error::Error>> {
+#[tokio::test]
+#[ignore = "requires network access"]
+async fn api_test_once() -> Result<(), Box<dyn std::error::Error>> {
     let client = Client::new();
     let url = "https://api-futures.kucoin.com/api/v1/level2/depth20?symbol=ETHUSDTM";
 
@@ -30,8 +34,9 @@ pub async fn api_test_once() -> Result<(), Box<dyn std::error::Error>> {
     if let Some(bid) = json_data["data"]["bids"].as_array() {
         println!("Top 5 Bids:");
         for bid in bid.iter().take(5) {
-            let price = bid[0].as_f64().unwrap_or(0.0);
-            let size = bid[1].as_i64().unwrap_or(0);
+            let (Some(price), Some(size)) = (coerce_f64(&bid[0]), coerce_i64(&bid[1])) else {
+                continue;
+            };
             println!("  Price: {}, Size: {}", &price, &size);
             bids.push((price, size));
         }
@@ -40,8 +45,9 @@ pub async fn api_test_once() -> Result<(), Box<dyn std::error::Error>> {
     if let Some(ask) = json_data["data"]["asks"].as_array() {
         println!("Top 5 Asks:");
         for ask in ask.iter().take(5) {
-            let price = ask[0].as_f64().unwrap_or(0.0);
-            let size = ask[1].as_i64().unwrap_or(0);
+            let (Some(price), Some(size)) = (coerce_f64(&ask[0]), coerce_i64(&ask[1])) else {
+                continue;
+            };
             println!("  Price: {}, Size: {}", &price, &size);
             asks.push((price, size));
         }
@@ -49,3 +55,1117 @@ pub async fn api_test_once() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+///Snapshots that a slow consumer cannot keep up with are dropped and counted
+#[tokio::test]
+async fn handle_text_counts_dropped_snapshots() {
+    use crate::api::handle_text;
+    use crate::config::FeedConfig;
+    use crate::emit::snapshot_channel;
+    use crate::order_book::OrderBook;
+    use crate::stats::ListenerStats;
+
+    let (tx, mut rx) = snapshot_channel(1);
+    let mut config = FeedConfig::default();
+    config.print.book = false;
+    let mut ob = OrderBook::new();
+    let mut stats = ListenerStats::new();
+
+    for price in 1..=3 {
+        let msg = format!(
+            r#"{{"type":"message","data":{{"bids":[["{}", 1]],"asks":[["100.0", 1]]}}}}"#,
+            price
+        );
+        handle_text(&mut ob, &msg, &config, &mut stats, Some(&tx));
+    }
+
+    assert_eq!(stats.updates_applied, 3);
+    assert_eq!(stats.snapshots_emitted, 3);
+    assert_eq!(stats.snapshots_dropped, 2);
+    assert_eq!(rx.recv().await.unwrap().bids, vec![(3.0, 1)]);
+}
+
+///A watch receiver always reads the latest snapshot, without counting the missed ones as dropped
+#[tokio::test]
+async fn handle_text_publishes_latest_snapshot_to_watch() {
+    use crate::api::handle_text;
+    use crate::config::FeedConfig;
+    use crate::emit::latest_snapshot_channel;
+    use crate::order_book::OrderBook;
+    use crate::stats::ListenerStats;
+
+    let (tx, mut rx) = latest_snapshot_channel();
+    let mut config = FeedConfig::default();
+    config.print.book = false;
+    let mut ob = OrderBook::new();
+    let mut stats = ListenerStats::new();
+
+    for price in 1..=3 {
+        let msg = format!(
+            r#"{{"type":"message","data":{{"bids":[["{}", 1]],"asks":[["100.0", 1]]}}}}"#,
+            price
+        );
+        handle_text(&mut ob, &msg, &config, &mut stats, Some(&tx));
+    }
+
+    assert_eq!(stats.snapshots_emitted, 3);
+    assert_eq!(stats.snapshots_dropped, 0);
+    assert!(rx.has_changed().unwrap());
+    assert_eq!(rx.borrow_and_update().bids, vec![(3.0, 1)]);
+}
+
+///Paused messages are read and counted but not applied, and updates resume once unpaused
+#[test]
+fn handle_text_discards_updates_while_paused() {
+    use crate::api::handle_text;
+    use crate::config::FeedConfig;
+    use crate::order_book::OrderBook;
+    use crate::stats::ListenerStats;
+
+    let mut config = FeedConfig::default();
+    config.print.book = false;
+    let pause = config.pause.clone();
+    let mut ob = OrderBook::new();
+    let mut stats = ListenerStats::new();
+    let update = |price: u32| {
+        format!(r#"{{"type":"message","data":{{"bids":[["{}", 1]],"asks":[["100.0", 1]]}}}}"#, price)
+    };
+
+    handle_text(&mut ob, &update(1), &config, &mut stats, None);
+    pause.pause();
+    handle_text(&mut ob, &update(2), &config, &mut stats, None);
+    handle_text(&mut ob, &update(3), &config, &mut stats, None);
+
+    assert_eq!(stats.messages_received, 3);
+    assert_eq!(stats.updates_applied, 1);
+    assert_eq!(ob.snapshot().bids, vec![(1.0, 1)]);
+
+    pause.resume();
+    handle_text(&mut ob, &update(4), &config, &mut stats, None);
+
+    assert_eq!(stats.updates_applied, 2);
+    assert_eq!(ob.snapshot().bids, vec![(4.0, 1)]);
+}
+
+#[test]
+fn malformed_token_response_is_token_fetch_error() {
+    use crate::api::parse_bullet;
+    use crate::error::FeedError;
+
+    assert!(matches!(parse_bullet("<html>503</html>"), Err(FeedError::TokenFetch(_))));
+    assert!(matches!(
+        parse_bullet(r#"{"code":"200000","data":{"instanceServers":[{"endpoint":"wss://ws"}]}}"#),
+        Err(FeedError::TokenFetch(_))
+    ));
+
+    let bullet = parse_bullet(
+        r#"{"code":"200000","data":{"token":"abc","instanceServers":[{"endpoint":"wss://ws"}]}}"#,
+    )
+    .unwrap();
+    assert_eq!(bullet.endpoint, "wss://ws");
+    assert_eq!(bullet.token, "abc");
+}
+
+///An empty `instanceServers` list is reported as such rather than as a missing URL
+#[test]
+fn empty_instance_servers_is_a_descriptive_error() {
+    use crate::api::parse_bullet;
+    use crate::error::FeedError;
+
+    let err = parse_bullet(r#"{"code":"200000","data":{"token":"abc","instanceServers":[]}}"#).unwrap_err();
+
+    let FeedError::TokenFetch(msg) = &err else { panic!("unexpected error {:?}", err) };
+    assert!(msg.contains("instanceServers is empty"), "unexpected message {}", msg);
+    assert_eq!(
+        err.to_string(),
+        "failed to fetch WebSocket token: no WebSocket server available, instanceServers is empty"
+    );
+}
+
+#[test]
+fn subscription_ids_are_unique_and_matched_by_ack() {
+    use crate::api::{is_ack_for, subscribe_message};
+
+    let (first_id, first_msg) = subscribe_message("/contractMarket/level2Depth5:ETHUSDTM");
+    let (second_id, _) = subscribe_message("/contractMarket/level2Depth5:ETHUSDTM");
+    assert_ne!(first_id, second_id);
+
+    let first: Value = serde_json::from_str(&first_msg).unwrap();
+    assert_eq!(first["id"], first_id.as_str());
+    assert_eq!(first["type"], "subscribe");
+
+    let ack = format!(r#"{{"id":"{}","type":"ack"}}"#, first_id);
+    assert!(is_ack_for(&ack, &first_id));
+    assert!(!is_ack_for(&ack, &second_id));
+    assert!(!is_ack_for(r#"{"id":"welcome","type":"welcome"}"#, &first_id));
+}
+
+///Serves a single HTTP request with `body` and returns the request's `User-Agent` header
+async fn serve_once(body: String) -> (String, tokio::task::JoinHandle<Option<String>>) {
+    let (endpoint, handle) = serve_n(body, 1).await;
+    (endpoint, tokio::spawn(async move { handle.await.unwrap().pop().flatten() }))
+}
+
+///Serves `n` HTTP requests with `body` and returns each request's `User-Agent` header
+async fn serve_n(body: String, n: usize) -> (String, tokio::task::JoinHandle<Vec<Option<String>>>) {
+    serve_routes(vec![("", body, Duration::ZERO)], n).await
+}
+
+///Serves `n` HTTP requests with the body of the first route whose path prefix matches the
+///request, after the route's delay, or a 404 when none does, and returns each request's
+///`User-Agent` header
+async fn serve_routes(
+    routes: Vec<(&'static str, String, Duration)>,
+    n: usize,
+) -> (String, tokio::task::JoinHandle<Vec<Option<String>>>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let mut user_agents = vec![];
+        for _ in 0..n {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let len = socket.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..len]).to_string();
+            let path = request.split_whitespace().nth(1).unwrap_or_default();
+            let (status, body) = match routes.iter().find(|(prefix, _, _)| path.starts_with(prefix)) {
+                Some((_, body, delay)) => {
+                    tokio::time::sleep(*delay).await;
+                    ("200 OK", body.as_str())
+                }
+                None => ("404 Not Found", ""),
+            };
+
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+
+            user_agents.push(
+                request
+                    .lines()
+                    .find(|line| line.to_ascii_lowercase().starts_with("user-agent:"))
+                    .map(|line| line["user-agent:".len()..].trim().to_string()),
+            );
+        }
+        user_agents
+    });
+    (endpoint, handle)
+}
+
+#[tokio::test]
+async fn fetch_snapshot_uses_injected_client() {
+    use crate::api::fetch_snapshot;
+    use crate::config::FeedConfig;
+
+    let (endpoint, server) =
+        serve_once(r#"{"code":"200000","data":{"bids":[[100.0,1]],"asks":[[101.0,2]]}}"#.to_string()).await;
+    let config = FeedConfig { rest_endpoint: endpoint, ..FeedConfig::default() };
+    let client = Client::builder().user_agent("orderbook-test/1.0").build().unwrap();
+
+    let snapshot = fetch_snapshot(&config, Some(client)).await.unwrap();
+
+    assert_eq!(server.await.unwrap().as_deref(), Some("orderbook-test/1.0"));
+    assert_eq!(snapshot.bids, vec![(100.0, 1)]);
+    assert_eq!(snapshot.asks, vec![(101.0, 2)]);
+}
+
+///The default depth-5 book is seeded from the depth-20 REST snapshot, the shallowest one served
+#[tokio::test]
+async fn fetch_snapshot_requests_a_served_depth_and_truncates() {
+    use crate::api::fetch_snapshot;
+    use crate::config::FeedConfig;
+
+    let bids: Vec<_> = (0..20).map(|i| serde_json::json!([100.0 - i as f64, 1])).collect();
+    let asks: Vec<_> = (0..20).map(|i| serde_json::json!([101.0 + i as f64, 1])).collect();
+    let body = serde_json::json!({"code": "200000", "data": {"bids": bids, "asks": asks}}).to_string();
+    let (endpoint, _server) =
+        serve_routes(vec![("/api/v1/level2/depth20?symbol=ETHUSDTM", body, Duration::ZERO)], 1).await;
+    let config = FeedConfig { rest_endpoint: endpoint, ..FeedConfig::default() };
+
+    let snapshot = fetch_snapshot(&config, None).await.unwrap();
+
+    assert_eq!(snapshot.bids.len(), 5);
+    assert_eq!(snapshot.asks.len(), 5);
+    assert_eq!(snapshot.bids.last(), Some(&(96.0, 1)));
+}
+
+#[test]
+fn bullet_response_carries_ping_timing() {
+    use std::time::Duration;
+    use crate::api::parse_bullet;
+
+    let bullet = parse_bullet(
+        r#"{"code":"200000","data":{"token":"abc","instanceServers":[{"endpoint":"wss://ws-api-futures.kucoin.com/","encrypt":true,"protocol":"websocket","pingInterval":50000,"pingTimeout":10000}]}}"#,
+    )
+    .unwrap();
+
+    assert_eq!(bullet.ping_interval, Duration::from_millis(50000));
+    assert_eq!(bullet.ping_timeout, Duration::from_millis(10000));
+}
+
+///Placeholder in a session's frames for the subscription acks
+const ACK: &str = "<ack>";
+///Placeholder in a session's frames for acking every subscription but the last, which gets an error
+const REJECT: &str = "<reject>";
+///Placeholder in a session's frames for a 200 ms pause before the next frame
+const GAP: &str = "<gap>";
+///Placeholder in a session's frames for the server closing the connection
+const CLOSE: &str = "<close>";
+
+///Serves a KuCoin-like feed: one bullet-public token over HTTP and one WebSocket connection per
+///session, sending the session's frames after acknowledging every subscription, or in place of the
+///[`ACK`] or [`REJECT`] placeholder if present. [`GAP`] and [`CLOSE`] pause and close the session. One more REST
+///request, such as a resnapshot, is answered too: depth snapshots are not served, so it gets a 404.
+///Returns the REST endpoint and, per session, whether the client closed the socket
+async fn mock_kucoin(sessions: Vec<Vec<String>>) -> (String, tokio::task::JoinHandle<Vec<bool>>) {
+    mock_kucoin_with_depth(sessions, None).await
+}
+
+///Like [`mock_kucoin`], answering requests for the depth20 and depth100 REST snapshots KuCoin
+///serves with `depth` when given, 100 ms late so that updates arrive while the snapshot is in flight
+async fn mock_kucoin_with_depth(
+    sessions: Vec<Vec<String>>,
+    depth: Option<String>,
+) -> (String, tokio::task::JoinHandle<Vec<bool>>) {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::protocol::Message;
+
+    let ws_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bullet = serde_json::json!({
+        "code": "200000",
+        "data": {
+            "token": "test-token",
+            "instanceServers": [{"endpoint": format!("ws://{}/", ws_listener.local_addr().unwrap())}]
+        }
+    });
+    let mut routes = vec![("/api/v1/bullet-public", bullet.to_string(), Duration::ZERO)];
+    if let Some(depth) = depth {
+        routes.push(("/api/v1/level2/depth20?", depth.clone(), Duration::from_millis(100)));
+        routes.push(("/api/v1/level2/depth100?", depth, Duration::from_millis(100)));
+    }
+    let (endpoint, _) = serve_routes(routes, sessions.len() + 1).await;
+
+    let handle = tokio::spawn(async move {
+        let mut connections = vec![];
+        for frames in sessions {
+            let (socket, _) = ws_listener.accept().await.unwrap();
+            connections.push(tokio::spawn(async move {
+                let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+
+                // The client sends its subscribe requests back to back, one per topic, though Nagle's
+                // algorithm may hold the later ones back for a delayed ACK
+                let Some(Ok(Message::Text(subscribe))) = ws.next().await else { return false };
+                let mut ids = vec![serde_json::from_str::<Value>(&subscribe).unwrap()["id"].clone()];
+                while let Ok(Some(Ok(Message::Text(subscribe)))) =
+                    tokio::time::timeout(Duration::from_millis(100), ws.next()).await
+                {
+                    ids.push(serde_json::from_str::<Value>(&subscribe).unwrap()["id"].clone());
+                }
+                let acks: Vec<Message> =
+                    ids.iter().map(|id| Message::Text(serde_json::json!({"id": id, "type": "ack"}).to_string())).collect();
+                if !frames.iter().any(|frame| frame == ACK || frame == REJECT) {
+                    for ack in &acks {
+                        ws.send(ack.clone()).await.unwrap();
+                    }
+                }
+
+                for frame in frames {
+                    let msg = match frame.as_str() {
+                        ACK => {
+                            for ack in &acks[..acks.len() - 1] {
+                                ws.send(ack.clone()).await.unwrap();
+                            }
+                            acks[acks.len() - 1].clone()
+                        }
+                        REJECT => {
+                            for ack in &acks[..acks.len() - 1] {
+                                ws.send(ack.clone()).await.unwrap();
+                            }
+                            let id = &ids[ids.len() - 1];
+                            Message::Text(serde_json::json!({"id": id, "type": "error", "code": 404}).to_string())
+                        }
+                        GAP => {
+                            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                            continue;
+                        }
+                        CLOSE => Message::Close(None),
+                        _ => Message::Text(frame),
+                    };
+                    if ws.send(msg).await.is_err() {
+                        break;
+                    }
+                }
+                while let Some(Ok(msg)) = ws.next().await {
+                    if msg.is_close() {
+                        return true;
+                    }
+                }
+                false
+            }));
+        }
+
+        let mut closed = vec![];
+        for connection in connections {
+            closed.push(connection.await.unwrap());
+        }
+        closed
+    });
+    (endpoint, handle)
+}
+
+///Truncated frames are counted, and a valid frame resets the consecutive count
+#[test]
+fn handle_text_counts_malformed_frames() {
+    use crate::api::handle_text;
+    use crate::config::FeedConfig;
+    use crate::order_book::OrderBook;
+    use crate::stats::ListenerStats;
+
+    let mut config = FeedConfig::default();
+    config.print.book = false;
+    let mut ob = OrderBook::new();
+    let mut stats = ListenerStats::new();
+
+    for _ in 0..2 {
+        handle_text(&mut ob, r#"{"type":"message","data":{"bids":[["100.0","#, &config, &mut stats, None);
+    }
+    assert_eq!(stats.parse_failures, 2);
+    assert_eq!(stats.consecutive_parse_failures, 2);
+
+    handle_text(&mut ob, r#"{"type":"pong"}"#, &config, &mut stats, None);
+    handle_text(&mut ob, "{", &config, &mut stats, None);
+    assert_eq!(stats.messages_received, 4);
+    assert_eq!(stats.parse_failures, 3);
+    assert_eq!(stats.consecutive_parse_failures, 1);
+}
+
+///Consecutive malformed frames past the threshold drop the connection and reconnect
+#[tokio::test]
+async fn malformed_frames_trigger_reconnect() {
+    use std::time::Duration;
+    use crate::api::start_websocket_listener;
+    use crate::config::{FeedConfig, PrintConfig, ReconnectConfig};
+    use crate::error::FeedError;
+    use crate::order_book::OrderBook;
+
+    let truncated = r#"{"type":"message","topic":"/contractMarket/level2Depth5:ETHUSDTM","data":{"bids":[[100"#;
+    let session = vec![truncated.to_string(); 3];
+    let (endpoint, server) = mock_kucoin(vec![session.clone(), session]).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        max_parse_failures: Some(3),
+        reconnect: ReconnectConfig { max_attempts: 1, delay: Duration::from_millis(10) },
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+    let mut ob = OrderBook::with_config(&config);
+
+    let err = start_websocket_listener(&mut ob, &config, None, None).await.unwrap_err();
+
+    let FeedError::ReconnectExhausted { attempts, last } = err else { panic!("unexpected error") };
+    assert_eq!(attempts, 1);
+    assert!(matches!(*last, FeedError::Parse(_)));
+    assert_eq!(server.await.unwrap(), vec![true, true], "both connections should be closed");
+}
+
+///A message over the size limit is rejected and counted, and the connection is closed
+#[tokio::test]
+async fn oversized_message_is_rejected() {
+    use crate::api::start_websocket_listener;
+    use crate::config::{FeedConfig, PrintConfig, ReconnectConfig};
+    use crate::order_book::OrderBook;
+
+    let oversized = format!(r#"{{"type":"message","padding":"{}"}}"#, "x".repeat(4096));
+    let (endpoint, server) = mock_kucoin(vec![vec![oversized, depth_frame(100.0, 1_000)]]).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        max_message_size: Some(1024),
+        max_frame_size: Some(1024),
+        reconnect: ReconnectConfig { max_attempts: 0, ..ReconnectConfig::default() },
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+    let mut ob = OrderBook::with_config(&config);
+
+    let stats = start_websocket_listener(&mut ob, &config, None, None).await.unwrap();
+
+    assert_eq!(stats.oversized_messages, 1);
+    assert_eq!(stats.updates_applied, 0);
+    assert_eq!(server.await.unwrap(), vec![true]);
+}
+
+///A depth-5 update whose only bid is at `bid`
+fn depth_frame(bid: f64, timestamp: i64) -> String {
+    serde_json::json!({
+        "type": "message",
+        "topic": "/contractMarket/level2Depth5:ETHUSDTM",
+        "data": {"bids": [[bid, 1]], "asks": [[150.0, 1]], "timestamp": timestamp}
+    })
+    .to_string()
+}
+
+#[tokio::test]
+async fn collect_updates_returns_first_n_snapshots() {
+    use crate::api::collect_updates_with;
+    use crate::config::{FeedConfig, PrintConfig};
+
+    let frames = (0..5).map(|i| depth_frame(100.0 + i as f64, 1_000 + i)).collect();
+    let (endpoint, server) = mock_kucoin(vec![frames]).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+
+    let updates = collect_updates_with(&config, None, 3).await.unwrap();
+
+    assert_eq!(updates.len(), 3);
+    let best_bids: Vec<f64> = updates.iter().map(|snapshot| snapshot.bids[0].0).collect();
+    assert_eq!(best_bids, vec![100.0, 101.0, 102.0]);
+    assert_eq!(server.await.unwrap(), vec![true], "client should close the connection");
+}
+
+#[tokio::test]
+async fn coalescing_applies_only_last_update_per_window() {
+    use std::time::Duration;
+    use crate::api::collect_updates_with;
+    use crate::config::{FeedConfig, PrintConfig};
+
+    let frames = (0..20).map(|i| depth_frame(100.0 + i as f64, 1_000 + i)).collect();
+    let (endpoint, server) = mock_kucoin(vec![frames]).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        coalesce_window: Some(Duration::from_millis(300)),
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+
+    // All 20 messages arrive within the first window, so the first applied update is the last one sent.
+    let updates = collect_updates_with(&config, None, 1).await.unwrap();
+
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].bids, vec![(119.0, 1)]);
+    assert_eq!(updates[0].timestamp, Some(1_019));
+    assert_eq!(server.await.unwrap(), vec![true]);
+}
+
+///A token refresh opens a second connection and closes the first one once it is subscribed
+#[tokio::test]
+async fn token_refresh_switches_to_new_connection() {
+    use std::time::Duration;
+    use crate::api::collect_updates_with;
+    use crate::config::{FeedConfig, PrintConfig};
+
+    // The first connection goes quiet, only the refreshed one delivers an update.
+    let (endpoint, server) = mock_kucoin(vec![vec![], vec![depth_frame(123.0, 2_000)]]).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        token_refresh: Some(Duration::from_millis(200)),
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+
+    let updates = tokio::time::timeout(Duration::from_secs(5), collect_updates_with(&config, None, 1))
+        .await
+        .expect("the refreshed connection should deliver the update")
+        .unwrap();
+
+    assert_eq!(updates[0].bids, vec![(123.0, 1)]);
+    assert_eq!(server.await.unwrap(), vec![true, true]);
+}
+
+///With trades tracked, updates are only processed once both subscriptions are acknowledged
+#[tokio::test]
+async fn every_subscription_is_acknowledged_before_updates() {
+    use crate::api::collect_updates_with;
+    use crate::config::{FeedConfig, PrintConfig};
+
+    let (endpoint, server) = mock_kucoin(vec![vec![depth_frame(100.0, 1_000)]]).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+    assert_eq!(config.topics().len(), 2);
+
+    let updates = collect_updates_with(&config, None, 1).await.unwrap();
+
+    assert_eq!(updates[0].bids, vec![(100.0, 1)]);
+    assert_eq!(server.await.unwrap(), vec![true]);
+}
+
+///A rejected trade subscription fails the session even though the book subscription was acked
+#[tokio::test]
+async fn rejected_second_subscription_fails_the_session() {
+    use crate::api::start_websocket_listener;
+    use crate::config::{FeedConfig, PrintConfig, ReconnectConfig};
+    use crate::error::FeedError;
+    use crate::order_book::OrderBook;
+
+    let (endpoint, _server) = mock_kucoin(vec![vec![REJECT.to_string(), depth_frame(100.0, 1_000)]]).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        reconnect: ReconnectConfig { max_attempts: 0, ..ReconnectConfig::default() },
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+    let mut ob = OrderBook::with_config(&config);
+
+    let err = start_websocket_listener(&mut ob, &config, None, None).await.unwrap_err();
+
+    assert!(matches!(&err, FeedError::Subscribe(text) if text.contains("error")), "unexpected error {:?}", err);
+    assert!(ob.snapshot().bids.is_empty(), "no update is applied before every subscription is acked");
+}
+
+///Updates racing the subscription ack are not applied
+#[tokio::test]
+async fn updates_before_ack_are_skipped() {
+    use crate::api::collect_updates_with;
+    use crate::config::{FeedConfig, PrintConfig};
+
+    let welcome = serde_json::json!({"id": "welcome-1", "type": "welcome"}).to_string();
+    let session = vec![welcome, depth_frame(100.0, 1_000), ACK.to_string(), depth_frame(101.0, 1_001)];
+    let (endpoint, server) = mock_kucoin(vec![session]).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+
+    let updates = collect_updates_with(&config, None, 1).await.unwrap();
+
+    assert_eq!(updates[0].bids, vec![(101.0, 1)]);
+    assert_eq!(server.await.unwrap(), vec![true]);
+}
+
+#[test]
+fn subscribe_request_sets_private_channel() {
+    use crate::api::{subscribe_message, subscribe_request};
+
+    let (_, private) = subscribe_request("/contractMarket/tradeOrders", true);
+    let private: Value = serde_json::from_str(&private).unwrap();
+    assert_eq!(private["privateChannel"], true);
+    assert_eq!(private["response"], true);
+
+    let (_, public) = subscribe_message("/contractMarket/level2Depth5:ETHUSDTM");
+    let public: Value = serde_json::from_str(&public).unwrap();
+    assert_eq!(public["privateChannel"], false);
+}
+
+///A trimmed `contracts/active` response, as recorded from the futures REST API
+const CONTRACTS_RESPONSE: &str = r#"{"code":"200000","data":[
+    {"symbol":"XBTUSDTM","rootSymbol":"USDT","type":"FFWCSX","baseCurrency":"XBT","quoteCurrency":"USDT","multiplier":0.001,"lotSize":1,"tickSize":0.1,"status":"Open"},
+    {"symbol":"ETHUSDTM","rootSymbol":"USDT","type":"FFWCSX","baseCurrency":"ETH","quoteCurrency":"USDT","multiplier":0.01,"lotSize":1,"tickSize":0.01,"status":"Open"},
+    {"symbol":"SOLUSDTM","rootSymbol":"USDT","type":"FFWCSX","baseCurrency":"SOL","quoteCurrency":"USDT","multiplier":0.1,"lotSize":1,"tickSize":0.001,"status":"Open"}
+]}"#;
+
+#[tokio::test]
+async fn fetch_active_contracts_lists_symbols() {
+    use crate::api::{fetch_active_contracts, parse_active_contracts};
+    use crate::config::FeedConfig;
+    use crate::error::FeedError;
+
+    let (endpoint, _server) = serve_once(CONTRACTS_RESPONSE.to_string()).await;
+    let config = FeedConfig { rest_endpoint: endpoint, ..FeedConfig::default() };
+
+    let symbols = fetch_active_contracts(&config, None).await.unwrap();
+
+    assert_eq!(symbols, vec!["XBTUSDTM", "ETHUSDTM", "SOLUSDTM"]);
+    assert!(matches!(parse_active_contracts("<html>503</html>"), Err(FeedError::Contracts(_))));
+    assert!(matches!(parse_active_contracts(r#"{"code":"400100"}"#), Err(FeedError::Contracts(_))));
+}
+
+#[tokio::test]
+async fn contract_multiplier_is_fetched_and_applied() {
+    use crate::api::{fetch_contract_multiplier, parse_contract_multipliers};
+    use crate::config::FeedConfig;
+    use crate::error::FeedError;
+    use crate::symbol::Symbol;
+
+    let multipliers = parse_contract_multipliers(CONTRACTS_RESPONSE).unwrap();
+    assert_eq!(multipliers["SOLUSDTM"], 0.1);
+    assert_eq!(multipliers.len(), 3);
+
+    let (endpoint, _server) = serve_n(CONTRACTS_RESPONSE.to_string(), 2).await;
+    let mut config = FeedConfig {
+        rest_endpoint: endpoint,
+        symbol: Symbol::new("SOLUSDTM").unwrap(),
+        multipliers: Default::default(),
+        ..FeedConfig::default()
+    };
+    assert_eq!(config.to_base_currency("SOLUSDTM", 30), 30.0);
+
+    let multiplier = fetch_contract_multiplier(&config, None).await.unwrap();
+    config.apply_multiplier(multiplier);
+    assert_eq!(config.to_base_currency("SOLUSDTM", 30), 3.0);
+
+    config.symbol = Symbol::new("DOGEUSDTM").unwrap();
+    assert!(matches!(fetch_contract_multiplier(&config, None).await, Err(FeedError::Contracts(_))));
+}
+
+///One connection carries depth and execution messages, each routed to its own state by topic
+#[test]
+fn interleaved_depth_and_execution_messages_are_routed_by_topic() {
+    use crate::api::{handle_text, Channel};
+    use crate::config::FeedConfig;
+    use crate::order_book::OrderBook;
+    use crate::stats::ListenerStats;
+
+    let mut config = FeedConfig::default();
+    config.print.book = false;
+    let mut ob = OrderBook::with_config(&config);
+    let mut stats = ListenerStats::new();
+    let trade = |price: &str| {
+        serde_json::json!({
+            "type": "message",
+            "topic": "/contractMarket/execution:ETHUSDTM",
+            "subject": "match",
+            "data": {"symbol": "ETHUSDTM", "side": "buy", "size": 3, "price": price, "ts": 1739942942550000000i64}
+        })
+        .to_string()
+    };
+
+    let frames = [depth_frame(100.0, 1_000), trade("150.5"), depth_frame(101.0, 1_001), trade("149.5")];
+    for frame in &frames {
+        handle_text(&mut ob, frame, &config, &mut stats, None);
+    }
+
+    assert_eq!(stats.updates_applied, 2, "only depth messages update the book");
+    assert_eq!(ob.snapshot().bids, vec![(101.0, 1)]);
+    assert_eq!(ob.last_trade(), Some(149.5));
+
+    let unknown = serde_json::json!({"type": "message", "topic": "/contractMarket/tickerV2:ETHUSDTM"});
+    assert_eq!(Channel::of(&unknown), None);
+    assert_eq!(
+        config.topics(),
+        vec![config.topic(), config.execution_topic()],
+        "both topics are subscribed on one connection"
+    );
+}
+
+///A re-delivered update is skipped by the listener: not counted as applied and not emitted
+#[tokio::test]
+async fn duplicate_sequence_is_not_applied_or_emitted() {
+    use crate::api::start_websocket_listener;
+    use crate::config::{FeedConfig, PrintConfig, ReconnectConfig};
+    use crate::emit::snapshot_channel;
+    use crate::order_book::OrderBook;
+
+    let delta = |sequence: u64, price: f64, size: i64| {
+        serde_json::json!({
+            "type": "message",
+            "topic": "/contractMarket/level2:ETHUSDTM",
+            "data": {"changes": {"bids": [[price, size]]}, "sequenceStart": sequence, "sequenceEnd": sequence}
+        })
+        .to_string()
+    };
+    let session = vec![delta(1, 100.0, 5), delta(2, 99.0, 3), delta(2, 99.0, 3), delta(3, 98.0, 1), CLOSE.to_string()];
+    let (endpoint, _server) = mock_kucoin(vec![session]).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        reconnect: ReconnectConfig { max_attempts: 0, ..ReconnectConfig::default() },
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+    let mut ob = OrderBook::with_config(&config);
+    let (tx, mut rx) = snapshot_channel(16);
+
+    let stats = start_websocket_listener(&mut ob, &config, None, Some(tx)).await.unwrap();
+
+    assert_eq!(stats.messages_received, 4);
+    assert_eq!(stats.updates_applied, 3);
+    assert_eq!(stats.stale_updates, 1);
+    assert_eq!(stats.snapshots_emitted, 3);
+    let mut emitted = vec![];
+    while let Some(snapshot) = rx.recv().await {
+        emitted.push(snapshot);
+    }
+    assert_eq!(emitted.len(), 3, "the duplicate must not reach consumers");
+    assert_eq!(ob.snapshot().bids, vec![(100.0, 5), (99.0, 3), (98.0, 1)]);
+}
+
+///Maintenance notices are reported to the status callback instead of being dropped
+#[test]
+fn status_frames_are_reported() {
+    use std::sync::{Arc, Mutex};
+    use crate::api::handle_text;
+    use crate::config::FeedConfig;
+    use crate::order_book::OrderBook;
+    use crate::stats::ListenerStats;
+    use crate::status::StatusHandler;
+
+    let seen = Arc::new(Mutex::new(vec![]));
+    let recorded = seen.clone();
+    let mut config = FeedConfig {
+        on_status: Some(StatusHandler::new(move |status| recorded.lock().unwrap().push(status.clone()))),
+        ..FeedConfig::default()
+    };
+    config.print.book = false;
+    let mut ob = OrderBook::with_config(&config);
+    let mut stats = ListenerStats::new();
+
+    let notice = r#"{"type":"notice","subject":"maintenance","data":{"status":"close","startAt":1739942942550}}"#;
+    handle_text(&mut ob, notice, &config, &mut stats, None);
+    handle_text(&mut ob, &depth_frame(100.0, 1_000), &config, &mut stats, None);
+    handle_text(&mut ob, r#"{"type":"pong","id":"1"}"#, &config, &mut stats, None);
+
+    assert_eq!(stats.status_messages, 1);
+    assert_eq!(stats.updates_applied, 1);
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 1);
+    assert_eq!(seen[0].subject, "maintenance");
+    assert_eq!(seen[0].data["status"], "close");
+}
+
+///With a SOCKS5 proxy configured, the WebSocket connection is tunneled through it
+#[tokio::test]
+async fn websocket_connects_through_socks5_proxy() {
+    use crate::api::collect_updates_with;
+    use crate::config::{FeedConfig, PrintConfig};
+    use crate::proxy_test::mock_socks5;
+
+    let (endpoint, server) = mock_kucoin(vec![vec![depth_frame(100.0, 1_000)]]).await;
+    let (proxy, targets) = mock_socks5(1).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        socks5_proxy: Some(proxy),
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+
+    let updates = collect_updates_with(&config, None, 1).await.unwrap();
+
+    assert_eq!(updates[0].bids, vec![(100.0, 1)]);
+    let targets = targets.await.unwrap();
+    assert_eq!(targets.len(), 1);
+    assert!(targets[0].starts_with("127.0.0.1:"), "unexpected target {}", targets[0]);
+    assert_eq!(server.await.unwrap(), vec![true]);
+}
+
+///The settled event fires once after a burst, when no update arrives for `settle_after`
+#[tokio::test]
+async fn settled_event_fires_once_after_a_burst() {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use crate::api::start_websocket_listener;
+    use crate::callback::Callback;
+    use crate::config::{FeedConfig, PrintConfig, ReconnectConfig};
+    use crate::order_book::OrderBook;
+    use crate::snapshot::OrderBookSnapshot;
+
+    let mut session: Vec<String> = (0..5).map(|i| depth_frame(100.0 + i as f64, 1_000 + i)).collect();
+    session.extend([GAP.to_string(), CLOSE.to_string()]);
+    let (endpoint, _server) = mock_kucoin(vec![session]).await;
+
+    let settled = Arc::new(Mutex::new(vec![]));
+    let recorded = settled.clone();
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        settle_after: Some(Duration::from_millis(50)),
+        on_settled: Some(Callback::new(move |snapshot: &OrderBookSnapshot| recorded.lock().unwrap().push(snapshot.clone()))),
+        reconnect: ReconnectConfig { max_attempts: 0, ..ReconnectConfig::default() },
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+    let mut ob = OrderBook::with_config(&config);
+
+    let stats = start_websocket_listener(&mut ob, &config, None, None).await.unwrap();
+
+    assert_eq!(stats.updates_applied, 5);
+    assert_eq!(stats.settled_events, 1);
+    let settled = settled.lock().unwrap();
+    assert_eq!(settled.len(), 1);
+    assert_eq!(settled[0].bids, vec![(104.0, 1)], "the settled book holds the last update of the burst");
+}
+
+///A book crossed for `resync_on_crossed` updates in a row is reloaded from the REST snapshot and resubscribed
+#[tokio::test]
+async fn persistently_crossed_book_triggers_resync() {
+    use crate::api::start_websocket_listener;
+    use crate::config::{FeedConfig, PrintConfig, ReconnectConfig};
+    use crate::order_book::OrderBook;
+
+    let crossed = |timestamp: i64| {
+        serde_json::json!({
+            "type": "message",
+            "topic": "/contractMarket/level2Depth5:ETHUSDTM",
+            "data": {"bids": [[151.0, 1]], "asks": [[150.0, 1]], "timestamp": timestamp}
+        })
+        .to_string()
+    };
+    let snapshot = r#"{"code":"200000","data":{"sequence":20,"bids":[[148.0,7]],"asks":[[149.0,3]]}}"#;
+    let sessions = vec![vec![crossed(1_000), crossed(1_001), crossed(1_002)], vec![CLOSE.to_string()]];
+    let (endpoint, server) = mock_kucoin_with_depth(sessions, Some(snapshot.to_string())).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        resync_on_crossed: Some(3),
+        reconnect: ReconnectConfig { max_attempts: 0, ..ReconnectConfig::default() },
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+    let mut ob = OrderBook::with_config(&config);
+
+    let stats = start_websocket_listener(&mut ob, &config, None, None).await.unwrap();
+
+    assert_eq!(stats.crossed_resyncs, 1);
+    assert_eq!(stats.updates_applied, 3);
+    assert!(!ob.is_crossed());
+    assert_eq!(ob.snapshot().bids, vec![(148.0, 7)], "the book is seeded from the REST snapshot");
+    assert_eq!(ob.snapshot().asks, vec![(149.0, 3)]);
+    assert_eq!(ob.last_sequence(), Some(20));
+    assert!(server.await.unwrap()[0], "the crossed connection should be closed by the client");
+}
+
+///A crossed book is only resynced once it stays crossed for the whole threshold
+#[test]
+fn crossed_streak_resets_on_uncrossed_update() {
+    use crate::api::handle_text;
+    use crate::config::FeedConfig;
+    use crate::order_book::OrderBook;
+    use crate::stats::ListenerStats;
+
+    let mut config = FeedConfig::default();
+    config.print.book = false;
+    let mut ob = OrderBook::with_config(&config);
+    let mut stats = ListenerStats::new();
+
+    handle_text(&mut ob, &depth_frame(155.0, 1_000), &config, &mut stats, None);
+    handle_text(&mut ob, &depth_frame(150.0, 1_001), &config, &mut stats, None);
+    assert_eq!(stats.consecutive_crossed, 2);
+    handle_text(&mut ob, &depth_frame(100.0, 1_002), &config, &mut stats, None);
+    assert_eq!(stats.consecutive_crossed, 0);
+}
+
+///The subscribe step runs in its own span, tagged with the symbol
+#[cfg(feature = "tracing")]
+#[tokio::test]
+#[tracing_test::traced_test]
+async fn subscribe_step_is_traced() {
+    use crate::api::collect_updates_with;
+    use crate::config::{FeedConfig, PrintConfig};
+
+    let (endpoint, _server) = mock_kucoin(vec![vec![depth_frame(100.0, 1_000)]]).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+
+    collect_updates_with(&config, None, 1).await.unwrap();
+
+    assert!(logs_contain("subscribe{symbol=ETHUSDTM}"));
+    assert!(logs_contain("subscription acknowledged"));
+}
+
+///Only updates that move the best bid or ask come out of the top of book stream
+#[tokio::test]
+async fn top_of_book_stream_skips_unchanged_touch() {
+    use futures_util::StreamExt;
+    use crate::api::top_of_book_stream_with;
+    use crate::config::{FeedConfig, PrintConfig, ReconnectConfig};
+
+    let deeper_bid = serde_json::json!({
+        "type": "message",
+        "topic": "/contractMarket/level2Depth5:ETHUSDTM",
+        "data": {"bids": [[100.0, 1], [99.0, 4]], "asks": [[150.0, 1]], "timestamp": 1_001}
+    })
+    .to_string();
+    let session = vec![
+        depth_frame(100.0, 1_000),
+        deeper_bid,
+        depth_frame(100.0, 1_002),
+        depth_frame(101.0, 1_003),
+        CLOSE.to_string(),
+    ];
+    let (endpoint, _server) = mock_kucoin(vec![session]).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        reconnect: ReconnectConfig { max_attempts: 0, ..ReconnectConfig::default() },
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+
+    let tops: Vec<_> = top_of_book_stream_with(config, None).collect().await;
+
+    assert_eq!(tops.len(), 2, "unexpected tops {:?}", tops);
+    assert_eq!(tops[0].best_bid, Some((100.0, 1)));
+    assert_eq!(tops[0].timestamp, Some(1_000));
+    assert_eq!(tops[1].best_bid, Some((101.0, 1)));
+    assert_eq!(tops[1].best_ask, Some((150.0, 1)));
+}
+
+///Deltas arriving while the resnapshot is in flight are replayed on top of it, skipping the stale ones
+#[tokio::test]
+async fn resnapshot_on_connect_replays_newer_deltas() {
+    use crate::api::start_websocket_listener;
+    use crate::config::{FeedConfig, PrintConfig, ReconnectConfig};
+    use crate::emit::snapshot_channel;
+    use crate::order_book::OrderBook;
+
+    let delta = |sequence: u64, side: &str, price: f64, size: i64| {
+        serde_json::json!({
+            "type": "message",
+            "topic": "/contractMarket/level2:ETHUSDTM",
+            "data": {"changes": {side: [[price, size]]}, "sequenceStart": sequence, "sequenceEnd": sequence}
+        })
+        .to_string()
+    };
+    let snapshot = r#"{"code":"200000","data":{"sequence":11,"bids":[[100.0,5],[99.0,3]],"asks":[[101.0,2]]}}"#;
+    let session = vec![
+        delta(10, "bids", 100.0, 1),
+        delta(12, "bids", 99.0, 0),
+        delta(13, "asks", 102.0, 4),
+        CLOSE.to_string(),
+    ];
+    let (endpoint, _server) = mock_kucoin_with_depth(vec![session], Some(snapshot.to_string())).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        resnapshot_on_connect: true,
+        reconnect: ReconnectConfig { max_attempts: 0, ..ReconnectConfig::default() },
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+    let mut ob = OrderBook::with_config(&config);
+    ob.update(vec![(90.0, 1)], vec![(120.0, 1)]);
+    let (tx, mut rx) = snapshot_channel(8);
+
+    let stats = start_websocket_listener(&mut ob, &config, None, Some(tx)).await.unwrap();
+
+    assert_eq!(ob.snapshot().bids, vec![(100.0, 5)], "the stale delta is already in the snapshot");
+    assert_eq!(ob.snapshot().asks, vec![(101.0, 2), (102.0, 4)]);
+    assert_eq!(ob.last_sequence(), Some(13));
+    assert_eq!(stats.messages_received, 3);
+    assert_eq!(stats.updates_replayed, 2, "the deltas were buffered during the resnapshot");
+    assert_eq!(stats.updates_applied, 0, "replays are not live updates");
+
+    let resynced = rx.recv().await.expect("the resynced book is emitted");
+    assert_eq!(resynced.bids, ob.snapshot().bids);
+    assert_eq!(resynced.asks, ob.snapshot().asks);
+    assert!(rx.recv().await.is_none());
+}
+
+///Replayed updates do not count towards `collect_updates`, which still returns `n` live updates
+#[tokio::test]
+async fn collect_updates_counts_only_live_updates_after_resnapshot() {
+    use crate::api::collect_updates_with;
+    use crate::config::{FeedConfig, PrintConfig};
+
+    let snapshot = r#"{"code":"200000","data":{"bids":[[99.0,5]],"asks":[[150.0,2]]}}"#;
+    let session = vec![depth_frame(100.0, 1_000), GAP.to_string(), depth_frame(101.0, 1_001), depth_frame(102.0, 1_002)];
+    let (endpoint, _server) = mock_kucoin_with_depth(vec![session], Some(snapshot.to_string())).await;
+    let config = FeedConfig {
+        rest_endpoint: endpoint,
+        track_trades: false,
+        resnapshot_on_connect: true,
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::default()
+    };
+
+    let updates = collect_updates_with(&config, None, 2).await.unwrap();
+
+    let best_bids: Vec<_> = updates.iter().map(|update| update.bids[0].0).collect();
+    assert_eq!(best_bids, vec![101.0, 102.0], "the replayed update at 100.0 is not one of the two");
+}
+
+///With `retain_last_message`, the raw text of the last processed frame is kept for inspection
+#[test]
+fn last_message_is_retained_when_enabled() {
+    use crate::api::handle_text;
+    use crate::config::FeedConfig;
+    use crate::order_book::OrderBook;
+    use crate::stats::ListenerStats;
+
+    let mut config = FeedConfig { retain_last_message: true, ..FeedConfig::default() };
+    config.print.book = false;
+    let mut ob = OrderBook::with_config(&config);
+    let mut stats = ListenerStats::new();
+
+    handle_text(&mut ob, &depth_frame(100.0, 1_000), &config, &mut stats, None);
+    let last = depth_frame(101.0, 1_001);
+    handle_text(&mut ob, &last, &config, &mut stats, None);
+    assert_eq!(stats.last_message.as_deref(), Some(last.as_str()));
+
+    config.retain_last_message = false;
+    let mut stats = ListenerStats::new();
+    handle_text(&mut ob, &last, &config, &mut stats, None);
+    assert_eq!(stats.last_message, None, "nothing is retained by default");
+}
+
+///The exchange listener subscribes with the venue's message and applies its frames, skipping stale deltas
+#[tokio::test]
+async fn exchange_listener_runs_a_bybit_feed() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::protocol::Message;
+    use crate::api::start_exchange_listener;
+    use crate::config::{FeedConfig, Market, PrintConfig, ReconnectConfig};
+    use crate::emit::snapshot_channel;
+    use crate::exchange::Bybit;
+    use crate::order_book::OrderBook;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bybit = Bybit { endpoint: format!("ws://{}/", listener.local_addr().unwrap()), ..Bybit::default() };
+    let frames = [
+        serde_json::json!({"success": true, "ret_msg": "", "op": "subscribe"}),
+        serde_json::json!({"topic": "orderbook.50.ETHUSDT", "type": "snapshot", "ts": 1_000,
+            "data": {"s": "ETHUSDT", "b": [["100.0", "1.5"]], "a": [["101.0", "2"]], "u": 10}}),
+        serde_json::json!({"topic": "orderbook.50.ETHUSDT", "type": "delta", "ts": 1_001,
+            "data": {"s": "ETHUSDT", "b": [["100.0", "0"]], "a": [], "u": 10}}),
+        serde_json::json!({"topic": "orderbook.50.ETHUSDT", "type": "delta", "ts": 1_002,
+            "data": {"s": "ETHUSDT", "b": [["100.5", "0.3"]], "a": [], "u": 11}}),
+    ];
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        let Some(Ok(Message::Text(subscribe))) = ws.next().await else { panic!("no subscribe request") };
+        for frame in frames {
+            ws.send(Message::Text(frame.to_string())).await.unwrap();
+        }
+        ws.send(Message::Close(None)).await.unwrap();
+        subscribe
+    });
+
+    let config = FeedConfig {
+        reconnect: ReconnectConfig { max_attempts: 0, ..ReconnectConfig::default() },
+        print: PrintConfig { book: false, raw_messages: false, ..PrintConfig::default() },
+        ..FeedConfig::new("ETHUSDT".parse().unwrap(), Market::Futures)
+    };
+    let mut ob = OrderBook::with_config(&config);
+    let (sender, mut receiver) = snapshot_channel(8);
+
+    let stats = start_exchange_listener(&mut ob, &bybit, &config, None, Some(sender)).await.unwrap();
+
+    let subscribe: Value = serde_json::from_str(&server.await.unwrap()).unwrap();
+    assert_eq!(subscribe["args"][0], "orderbook.50.ETHUSDT");
+    assert_eq!(stats.messages_received, 4);
+    assert_eq!(stats.updates_applied, 2);
+    assert_eq!(stats.stale_updates, 1);
+
+    let mut best_bids = vec![];
+    while let Some(snapshot) = receiver.recv().await {
+        best_bids.push(snapshot.bids[0]);
+    }
+    assert_eq!(best_bids, vec![(100.0, 150), (100.5, 30)], "the stale delta did not remove the level at 100.0");
+}
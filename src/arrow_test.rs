@@ -0,0 +1,31 @@
+//!Local testing
+//!This tests the Arrow export of the book
+
+use arrow_array::{Array, Float64Array, Int64Array, StringArray};
+use arrow_schema::DataType;
+
+use crate::order_book::OrderBook;
+
+#[test]
+fn to_arrow_has_schema_and_one_row_per_level() {
+    let mut ob = OrderBook::new();
+    ob.update(vec![(1999.0, 5), (2000.0, 15)], vec![(2001.0, 3), (2002.0, 4), (2003.0, 1)]);
+
+    let batch = ob.to_arrow();
+
+    let schema = batch.schema();
+    let columns: Vec<(&str, &DataType)> =
+        schema.fields().iter().map(|field| (field.name().as_str(), field.data_type())).collect();
+    assert_eq!(
+        columns,
+        vec![("side", &DataType::Utf8), ("price", &DataType::Float64), ("size", &DataType::Int64)]
+    );
+    assert_eq!(batch.num_rows(), 5);
+
+    let sides = batch.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+    let prices = batch.column(1).as_any().downcast_ref::<Float64Array>().unwrap();
+    let sizes = batch.column(2).as_any().downcast_ref::<Int64Array>().unwrap();
+    assert_eq!((sides.value(0), prices.value(0), sizes.value(0)), ("bid", 2000.0, 15));
+    assert_eq!((sides.value(2), prices.value(2), sizes.value(2)), ("ask", 2001.0, 3));
+    assert_eq!(sides.null_count(), 0);
+}
@@ -0,0 +1,46 @@
+//! Callbacks that can be stored in a `FeedConfig`, which is `Clone`, `Debug` and `PartialEq`.
+
+use std::fmt;
+use std::sync::Arc;
+
+/// A shared callback invoked with a `&T`, e.g. a [`crate::status::SystemStatus`].
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::callback::Callback;
+/// let callback = Callback::new(|value: &u32| assert_eq!(*value, 7));
+/// callback.call(&7);
+/// ```
+pub struct Callback<T>(Arc<dyn Fn(&T) + Send + Sync>);
+
+impl<T> Callback<T> {
+    /// Wraps `callback` so it can be stored in a `FeedConfig`.
+    pub fn new(callback: impl Fn(&T) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    /// Invokes the callback.
+    pub fn call(&self, value: &T) {
+        (self.0)(value)
+    }
+}
+
+impl<T> Clone for Callback<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T> fmt::Debug for Callback<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Callback(..)")
+    }
+}
+
+/// Two callbacks are equal when they share the same function.
+impl<T> PartialEq for Callback<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
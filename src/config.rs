@@ -0,0 +1,456 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use crate::callback::Callback;
+use crate::parse::{DepthKeys, LevelLayout};
+use crate::snapshot::OrderBookSnapshot;
+use crate::status::StatusHandler;
+use crate::symbol::Symbol;
+use std::time::Duration;
+
+/// Which KuCoin market a feed belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Market {
+    Spot,
+    Futures,
+}
+
+/// Which depth snapshot channel a feed subscribes to, e.g. `/contractMarket/level2Depth50:{symbol}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DepthChannel {
+    /// The best 5 levels per side.
+    #[default]
+    Depth5,
+    /// The best 50 levels per side.
+    Depth50,
+}
+
+impl DepthChannel {
+    /// The number of levels per side the channel sends.
+    pub fn depth(self) -> usize {
+        match self {
+            DepthChannel::Depth5 => 5,
+            DepthChannel::Depth50 => 50,
+        }
+    }
+}
+
+impl FromStr for DepthChannel {
+    type Err = String;
+
+    /// Parses the `--depth` value: `5` or `50`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "5" => Ok(DepthChannel::Depth5),
+            "50" => Ok(DepthChannel::Depth50),
+            other => Err(format!("unsupported depth `{}`, expected 5 or 50", other)),
+        }
+    }
+}
+
+/// How the listener behaves after the WebSocket connection is lost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectConfig {
+    /// How many consecutive reconnects to attempt before giving up. `0` disables reconnecting.
+    pub max_attempts: u32,
+    /// How long to wait before each reconnect attempt.
+    pub delay: Duration,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// How the order book is printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// A prettytable (or plain-text) table of every level.
+    #[default]
+    Table,
+    /// The snapshot serialized as one JSON object per line.
+    Json,
+    /// A single best bid/offer line.
+    Compact,
+    /// A single line with the best bid, best ask, spread and mid, see `PrintConfig::in_place`.
+    Ticker,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    /// Parses the `--output` value: `table`, `json` (or its alias `ndjson`), `compact` or `ticker`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "json" | "ndjson" => Ok(OutputFormat::Json),
+            "compact" => Ok(OutputFormat::Compact),
+            "ticker" => Ok(OutputFormat::Ticker),
+            other => Err(format!("unknown output format `{}`, expected table, ndjson, compact or ticker", other)),
+        }
+    }
+}
+
+/// What the listener writes to stdout.
+///
+/// Only the order book is written to stdout; connection status and raw messages go to stderr,
+/// so the JSON output can be piped into tools such as `jq`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrintConfig {
+    /// Print the order book after every applied update.
+    pub book: bool,
+    /// The format the order book is printed in.
+    pub format: OutputFormat,
+    /// Print every raw WebSocket message to stderr as it arrives.
+    pub raw_messages: bool,
+    /// Highlight table levels whose size exceeds this many contracts. `None` disables highlighting.
+    pub wall_threshold: Option<i64>,
+    /// Rewrite the ticker line in place with a carriage return instead of printing a new line
+    /// per update. Only applies to [`OutputFormat::Ticker`].
+    pub in_place: bool,
+}
+
+impl Default for PrintConfig {
+    fn default() -> Self {
+        Self {
+            book: true,
+            format: OutputFormat::Table,
+            raw_messages: true,
+            wall_threshold: None,
+            in_place: false,
+        }
+    }
+}
+
+/// A shared switch that freezes the listener without dropping the connection.
+///
+/// Clones share the same flag, so a UI can keep one handle while the listener holds another.
+/// While paused, messages are still read (keeping the socket drained) but are discarded instead of
+/// being applied, printed or emitted.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::config::PauseControl;
+/// let pause = PauseControl::default();
+/// let handle = pause.clone();
+/// handle.pause();
+/// assert!(pause.is_paused());
+/// handle.resume();
+/// assert!(!pause.is_paused());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PauseControl(Arc<AtomicBool>);
+
+impl PauseControl {
+    /// Stops applying updates until [`PauseControl::resume`] is called.
+    pub fn pause(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Resumes applying updates.
+    pub fn resume(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    /// Whether updates are currently being discarded.
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Two controls are equal when they are in the same state.
+impl PartialEq for PauseControl {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_paused() == other.is_paused()
+    }
+}
+
+/// Everything needed to track one symbol's order book.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::config::{FeedConfig, Market};
+/// # use warmup_project::symbol::Symbol;
+/// let config = FeedConfig::new(Symbol::new("XBTUSDTM").unwrap(), Market::Futures);
+/// assert_eq!(config.topic(), "/contractMarket/level2Depth5:XBTUSDTM");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedConfig {
+    pub symbol: Symbol,
+    pub market: Market,
+    /// Number of price levels kept on each side of the book.
+    pub depth: usize,
+    /// The depth snapshot channel subscribed to, which selects the depth topic. See
+    /// [`FeedConfig::set_depth_channel`] to keep as many levels as the channel sends.
+    pub depth_channel: DepthChannel,
+    /// Base URL of the REST API, e.g. `https://api-futures.kucoin.com`.
+    pub rest_endpoint: String,
+    pub reconnect: ReconnectConfig,
+    pub print: PrintConfig,
+    /// Base-currency units per contract, keyed by symbol. Unknown symbols use `1.0`.
+    pub multipliers: HashMap<String, f64>,
+    /// Hard cap on the levels kept per side by incremental (delta) updates.
+    pub max_levels: usize,
+    /// Number of spread samples retained by the order book.
+    pub spread_history: usize,
+    /// Number of sequenced snapshots retained by the order book for
+    /// [`crate::order_book::OrderBook::snapshot_at_sequence`]. `0` records none.
+    pub snapshot_history: usize,
+    /// Also subscribe to the trade channel to track the last traded price.
+    pub track_trades: bool,
+    /// How far back trades count towards the rolling trade VWAP.
+    pub trade_vwap_window: Duration,
+    /// When set, book updates are buffered and only the latest one of each window is applied,
+    /// printed and emitted. `None` applies every update as it arrives.
+    pub coalesce_window: Option<Duration>,
+    /// How long after it was issued the WebSocket token is proactively replaced, by opening a new
+    /// connection before closing the old one. KuCoin tokens are valid for 24 hours. `None` keeps
+    /// the token until the server drops the connection.
+    pub token_refresh: Option<Duration>,
+    /// Sets `privateChannel` on subscriptions, for private feeds that require an authenticated token.
+    pub private_channel: bool,
+    /// Freezes processing while set; see [`PauseControl`].
+    pub pause: PauseControl,
+    /// The keys under `data` holding the bid and ask levels of depth messages.
+    pub depth_keys: DepthKeys,
+    /// The positions of the price and the size within each level of depth messages.
+    pub level_layout: LevelLayout,
+    /// After this many consecutive frames that are not valid JSON the stream is assumed to be
+    /// desynchronized and the connection is dropped and reconnected. `None` never reconnects.
+    pub max_parse_failures: Option<u64>,
+    /// Levels that are not set again within this long are pruned as stale, in case their removal
+    /// was missed by the incremental feed. `None` keeps levels until they are removed.
+    pub level_ttl: Option<Duration>,
+    /// The largest WebSocket message accepted, in bytes. Larger messages drop the connection
+    /// instead of being buffered. `None` removes the limit.
+    pub max_message_size: Option<usize>,
+    /// The largest single WebSocket frame accepted, in bytes. `None` removes the limit.
+    pub max_frame_size: Option<usize>,
+    /// Called with every system or status frame, such as a maintenance notice. These frames are
+    /// logged either way.
+    pub on_status: Option<StatusHandler>,
+    /// A SOCKS5 proxy (`host:port`) to tunnel the WebSocket connection through. The token and
+    /// REST requests use the `reqwest` client passed to the listener, which has its own proxy
+    /// settings.
+    pub socks5_proxy: Option<String>,
+    /// How long the book must go without an applied update to count as settled, e.g. after a
+    /// burst. `None` disables the settled event.
+    pub settle_after: Option<Duration>,
+    /// Called with a snapshot of the book each time it settles, see `settle_after`.
+    pub on_settled: Option<Callback<OrderBookSnapshot>>,
+    /// After this many consecutive updates leaving the book crossed, it is assumed to be
+    /// desynchronized: a REST snapshot is applied and the topics are resubscribed on a new
+    /// connection. `None` leaves a crossed book as it is.
+    pub resync_on_crossed: Option<u64>,
+    /// Levels of a depth snapshot priced further than this percentage from the current mid are
+    /// dropped as fat-finger or glitch prices. `None` accepts every price.
+    pub max_price_deviation: Option<f64>,
+    /// After each (re)connect, fetch a REST snapshot while buffering the book updates that arrive
+    /// in the meantime, then apply the snapshot and the buffered updates newer than it, so
+    /// incremental feeds miss nothing across the reconnect.
+    pub resnapshot_on_connect: bool,
+    /// Keep a copy of the last processed text frame in `ListenerStats::last_message`, for
+    /// debugging. Off by default to save an allocation per message.
+    pub retain_last_message: bool,
+}
+
+impl FeedConfig {
+    /// Creates a depth-5 config for `symbol` using the default endpoints of `market`.
+    pub fn new(symbol: Symbol, market: Market) -> Self {
+        let rest_endpoint = match market {
+            Market::Spot => "https://api.kucoin.com",
+            Market::Futures => "https://api-futures.kucoin.com",
+        };
+
+        Self {
+            symbol,
+            market,
+            depth: 5,
+            depth_channel: DepthChannel::Depth5,
+            rest_endpoint: rest_endpoint.to_string(),
+            reconnect: ReconnectConfig::default(),
+            print: PrintConfig::default(),
+            multipliers: default_multipliers(),
+            max_levels: 1000,
+            spread_history: 100,
+            snapshot_history: 0,
+            track_trades: true,
+            trade_vwap_window: Duration::from_secs(60),
+            coalesce_window: None,
+            token_refresh: Some(Duration::from_secs(23 * 60 * 60)),
+            private_channel: false,
+            pause: PauseControl::default(),
+            depth_keys: DepthKeys::default(),
+            level_layout: LevelLayout::default(),
+            max_parse_failures: Some(5),
+            level_ttl: None,
+            max_message_size: Some(1 << 20),
+            max_frame_size: Some(1 << 20),
+            on_status: None,
+            socks5_proxy: None,
+            settle_after: None,
+            on_settled: None,
+            resync_on_crossed: None,
+            max_price_deviation: None,
+            resnapshot_on_connect: false,
+            retain_last_message: false,
+        }
+    }
+
+    /// The contract multiplier registered for `symbol`, or `1.0` if it is unknown.
+    pub fn multiplier_for(&self, symbol: &str) -> f64 {
+        self.multipliers.get(symbol).copied().unwrap_or(1.0)
+    }
+
+    /// Registers `multiplier` for this config's symbol, e.g. one fetched with
+    /// [`crate::api::fetch_contract_multiplier`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::config::FeedConfig;
+    /// let mut config = FeedConfig::default();
+    /// config.apply_multiplier(0.1);
+    /// assert_eq!(config.to_base_currency("ETHUSDTM", 250), 25.0);
+    /// ```
+    pub fn apply_multiplier(&mut self, multiplier: f64) {
+        self.multipliers.insert(self.symbol.to_string(), multiplier);
+    }
+
+    /// Subscribes to the depth snapshot channel `channel` and keeps as many levels in the book.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::config::{DepthChannel, FeedConfig};
+    /// let mut config = FeedConfig::default();
+    /// config.set_depth_channel(DepthChannel::Depth50);
+    /// assert_eq!(config.topic(), "/contractMarket/level2Depth50:ETHUSDTM");
+    /// assert_eq!(config.depth, 50);
+    /// ```
+    pub fn set_depth_channel(&mut self, channel: DepthChannel) {
+        self.depth_channel = channel;
+        self.depth = channel.depth();
+    }
+
+    /// Converts a size in contracts of `symbol` into base-currency units.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::config::FeedConfig;
+    /// let config = FeedConfig::default();
+    /// assert_eq!(config.to_base_currency("ETHUSDTM", 250), 2.5);
+    /// ```
+    pub fn to_base_currency(&self, symbol: &str, contracts: i64) -> f64 {
+        contracts as f64 * self.multiplier_for(symbol)
+    }
+
+    /// The WebSocket topic carrying depth snapshots for this symbol, on `depth_channel`.
+    pub fn topic(&self) -> String {
+        let depth = self.depth_channel.depth();
+        match self.market {
+            Market::Spot => format!("/spotMarket/level2Depth{}:{}", depth, self.symbol),
+            Market::Futures => format!("/contractMarket/level2Depth{}:{}", depth, self.symbol),
+        }
+    }
+
+    /// The WebSocket topic carrying trade executions for this symbol.
+    pub fn execution_topic(&self) -> String {
+        match self.market {
+            Market::Spot => format!("/market/match:{}", self.symbol),
+            Market::Futures => format!("/contractMarket/execution:{}", self.symbol),
+        }
+    }
+
+    /// Every topic a session subscribes to: the depth topic, then the execution topic when
+    /// `track_trades` is set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::config::FeedConfig;
+    /// let config = FeedConfig::default();
+    /// assert_eq!(
+    ///     config.topics(),
+    ///     vec!["/contractMarket/level2Depth5:ETHUSDTM", "/contractMarket/execution:ETHUSDTM"]
+    /// );
+    /// ```
+    pub fn topics(&self) -> Vec<String> {
+        let mut topics = vec![self.topic()];
+        if self.track_trades {
+            topics.push(self.execution_topic());
+        }
+        topics
+    }
+
+    /// The REST endpoint handing out public WebSocket tokens.
+    pub fn token_url(&self) -> String {
+        format!("{}/api/v1/bullet-public", self.rest_endpoint)
+    }
+
+    /// The REST endpoint listing the tradeable futures contracts.
+    pub fn contracts_url(&self) -> String {
+        format!("{}/api/v1/contracts/active", self.rest_endpoint)
+    }
+
+    /// The smallest depth the REST API serves snapshots at that covers `depth`: `20` or `100`.
+    ///
+    /// Books deeper than 100 levels are seeded with the best 100.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::config::FeedConfig;
+    /// let mut config = FeedConfig::default();
+    /// assert_eq!(config.snapshot_depth(), 20);
+    /// config.depth = 50;
+    /// assert_eq!(config.snapshot_depth(), 100);
+    /// ```
+    pub fn snapshot_depth(&self) -> usize {
+        if self.depth <= 20 {
+            20
+        } else {
+            100
+        }
+    }
+
+    /// The REST endpoint serving a depth snapshot for this symbol, at [`FeedConfig::snapshot_depth`].
+    pub fn depth_url(&self) -> String {
+        match self.market {
+            Market::Spot => format!(
+                "{}/api/v1/market/orderbook/level2_{}?symbol={}",
+                self.rest_endpoint,
+                self.snapshot_depth(),
+                self.symbol
+            ),
+            Market::Futures => format!(
+                "{}/api/v1/level2/depth{}?symbol={}",
+                self.rest_endpoint,
+                self.snapshot_depth(),
+                self.symbol
+            ),
+        }
+    }
+}
+
+/// Multipliers of the commonly tracked KuCoin futures contracts.
+fn default_multipliers() -> HashMap<String, f64> {
+    HashMap::from([
+        ("ETHUSDTM".to_string(), 0.01),
+        ("XBTUSDTM".to_string(), 0.001),
+    ])
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        Self::new(Symbol::new("ETHUSDTM").expect("valid symbol"), Market::Futures)
+    }
+}
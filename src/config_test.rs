@@ -0,0 +1,104 @@
+//!Local testing
+//!This tests the feed configuration
+
+use crate::config::{FeedConfig, Market};
+use crate::order_book::OrderBook;
+
+#[test]
+fn futures_config_derives_topic_and_urls() {
+    let config = FeedConfig::new("XBTUSDTM".parse().unwrap(), Market::Futures);
+
+    assert_eq!(config.topic(), "/contractMarket/level2Depth5:XBTUSDTM");
+    assert_eq!(config.token_url(), "https://api-futures.kucoin.com/api/v1/bullet-public");
+    assert_eq!(config.depth_url(), "https://api-futures.kucoin.com/api/v1/level2/depth20?symbol=XBTUSDTM");
+}
+
+#[test]
+fn spot_config_derives_topic_and_urls() {
+    use crate::config::DepthChannel;
+
+    let mut config = FeedConfig::new("BTC-USDT".parse().unwrap(), Market::Spot);
+    config.set_depth_channel(DepthChannel::Depth50);
+
+    assert_eq!(config.topic(), "/spotMarket/level2Depth50:BTC-USDT");
+    assert_eq!(config.token_url(), "https://api.kucoin.com/api/v1/bullet-public");
+    assert_eq!(config.depth_url(), "https://api.kucoin.com/api/v1/market/orderbook/level2_100?symbol=BTC-USDT");
+}
+
+#[test]
+fn snapshot_depth_rounds_up_to_a_served_rest_depth() {
+    let mut config = FeedConfig::new("XBTUSDTM".parse().unwrap(), Market::Futures);
+
+    for (depth, expected) in [(1, 20), (5, 20), (20, 20), (21, 100), (50, 100), (100, 100), (200, 100)] {
+        config.depth = depth;
+        assert_eq!(config.snapshot_depth(), expected, "depth {}", depth);
+    }
+}
+
+#[test]
+fn order_book_takes_symbol_and_depth_from_config() {
+    let mut config = FeedConfig::new("XBTUSDTM".parse().unwrap(), Market::Futures);
+    config.depth = 2;
+    let mut ob = OrderBook::with_config(&config);
+
+    ob.update(vec![(1.0, 1), (2.0, 1), (3.0, 1)], vec![(4.0, 1), (5.0, 1), (6.0, 1)]);
+
+    assert_eq!(ob.symbol(), "XBTUSDTM");
+    assert_eq!(ob.snapshot().bids, vec![(3.0, 1), (2.0, 1)]);
+    assert_eq!(ob.snapshot().asks, vec![(4.0, 1), (5.0, 1)]);
+}
+
+#[test]
+fn multiplier_map_drives_base_currency_conversion() {
+    let mut config = FeedConfig::new("SOLUSDTM".parse().unwrap(), Market::Futures);
+    config.multipliers.insert("SOLUSDTM".to_string(), 0.1);
+
+    assert_eq!(config.to_base_currency("SOLUSDTM", 30), 3.0);
+    assert_eq!(config.multiplier_for("UNKNOWNUSDTM"), 1.0);
+    assert_eq!(config.to_base_currency("UNKNOWNUSDTM", 30), 30.0);
+
+    let ob = OrderBook::with_config(&config);
+    assert_eq!(ob.to_base_currency(30), 3.0);
+}
+
+#[test]
+fn output_format_parses_cli_values() {
+    use crate::config::OutputFormat;
+
+    assert_eq!("ndjson".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+    assert_eq!("json".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+    assert_eq!("Table".parse::<OutputFormat>(), Ok(OutputFormat::Table));
+    assert_eq!("compact".parse::<OutputFormat>(), Ok(OutputFormat::Compact));
+    assert_eq!("ticker".parse::<OutputFormat>(), Ok(OutputFormat::Ticker));
+    assert!("yaml".parse::<OutputFormat>().is_err());
+}
+
+#[test]
+fn depth50_channel_selects_topic_and_book_depth() {
+    use crate::config::DepthChannel;
+
+    let mut config = FeedConfig::new("XBTUSDTM".parse().unwrap(), Market::Futures);
+    config.set_depth_channel(DepthChannel::Depth50);
+    assert_eq!(config.topic(), "/contractMarket/level2Depth50:XBTUSDTM");
+    assert_eq!("50".parse::<DepthChannel>(), Ok(DepthChannel::Depth50));
+    assert!("20".parse::<DepthChannel>().is_err());
+
+    let mut ob = OrderBook::with_config(&config);
+    let bids = (0..60).map(|i| (1000.0 - i as f64, 1)).collect();
+    let asks = (0..60).map(|i| (1001.0 + i as f64, 1)).collect();
+    ob.update(bids, asks);
+
+    assert_eq!(ob.depth(), 50);
+    assert_eq!(ob.snapshot().bids.len(), 50);
+    assert_eq!(ob.snapshot().asks.len(), 50);
+    assert_eq!(ob.snapshot().bids.last(), Some(&(951.0, 1)));
+}
+
+#[test]
+fn depth_only_truncates_and_never_changes_the_topic() {
+    let mut config = FeedConfig::new("XBTUSDTM".parse().unwrap(), Market::Futures);
+    config.depth = 20;
+
+    assert_eq!(config.topic(), "/contractMarket/level2Depth5:XBTUSDTM");
+    assert_eq!(OrderBook::with_config(&config).depth(), 20);
+}
@@ -0,0 +1,132 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::{watch, Notify};
+use crate::snapshot::OrderBookSnapshot;
+
+struct Shared {
+    queue: Mutex<VecDeque<OrderBookSnapshot>>,
+    capacity: usize,
+    notify: Notify,
+    closed: AtomicBool,
+}
+
+/// Sending half of a snapshot channel. Never blocks the caller.
+///
+/// Either feeds the queue of a [`snapshot_channel`], or publishes to a `watch` channel created
+/// with [`latest_snapshot_channel`].
+pub struct SnapshotSender {
+    inner: Inner,
+}
+
+enum Inner {
+    Queue(Arc<Shared>),
+    Latest(watch::Sender<OrderBookSnapshot>),
+}
+
+/// Receiving half of a snapshot channel.
+pub struct SnapshotReceiver {
+    shared: Arc<Shared>,
+}
+
+/// Creates a bounded, coalescing snapshot channel.
+///
+/// Unlike a plain `mpsc` channel, a full queue never blocks the sender: the
+/// oldest queued snapshot is discarded to make room for the new one, since
+/// only the latest state of the book matters to consumers.
+///
+/// # Arguments
+///
+/// * `capacity` - The maximum number of queued snapshots (at least 1).
+pub fn snapshot_channel(capacity: usize) -> (SnapshotSender, SnapshotReceiver) {
+    let capacity = capacity.max(1);
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::with_capacity(capacity)),
+        capacity,
+        notify: Notify::new(),
+        closed: AtomicBool::new(false),
+    });
+    (
+        SnapshotSender { inner: Inner::Queue(shared.clone()) },
+        SnapshotReceiver { shared },
+    )
+}
+
+/// Creates a channel that only ever holds the latest snapshot.
+///
+/// Receivers read the freshest state of the book with `borrow` or wait for the next one with
+/// `changed`. A lagging receiver simply misses the intermediate snapshots, which are never
+/// counted as dropped.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::emit::latest_snapshot_channel;
+/// # use warmup_project::snapshot::OrderBookSnapshot;
+/// let (tx, rx) = latest_snapshot_channel();
+/// tx.try_send(OrderBookSnapshot { bids: vec![(2000.0, 15)], ..Default::default() });
+/// assert_eq!(rx.borrow().bids, vec![(2000.0, 15)]);
+/// ```
+pub fn latest_snapshot_channel() -> (SnapshotSender, watch::Receiver<OrderBookSnapshot>) {
+    let (tx, rx) = watch::channel(OrderBookSnapshot::default());
+    (SnapshotSender::from(tx), rx)
+}
+
+impl From<watch::Sender<OrderBookSnapshot>> for SnapshotSender {
+    fn from(sender: watch::Sender<OrderBookSnapshot>) -> Self {
+        SnapshotSender { inner: Inner::Latest(sender) }
+    }
+}
+
+impl SnapshotSender {
+    /// Queues a snapshot without waiting for the consumer.
+    ///
+    /// Returns `true` if the queue was full and a stale snapshot was dropped.
+    pub fn try_send(&self, snapshot: OrderBookSnapshot) -> bool {
+        let shared = match &self.inner {
+            Inner::Queue(shared) => shared,
+            Inner::Latest(sender) => {
+                sender.send_replace(snapshot);
+                return false;
+            }
+        };
+
+        let dropped = {
+            let mut queue = shared.queue.lock().unwrap();
+            let dropped = queue.len() >= shared.capacity;
+            if dropped {
+                queue.pop_front();
+            }
+            queue.push_back(snapshot);
+            dropped
+        };
+        shared.notify.notify_one();
+        dropped
+    }
+}
+
+impl Drop for SnapshotSender {
+    fn drop(&mut self) {
+        if let Inner::Queue(shared) = &self.inner {
+            shared.closed.store(true, Ordering::Release);
+            shared.notify.notify_one();
+        }
+    }
+}
+
+impl SnapshotReceiver {
+    /// Waits for the next snapshot.
+    ///
+    /// Returns `None` once the sender is dropped and the queue is drained.
+    pub async fn recv(&mut self) -> Option<OrderBookSnapshot> {
+        loop {
+            if let Some(snapshot) = self.shared.queue.lock().unwrap().pop_front() {
+                return Some(snapshot);
+            }
+            if self.shared.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            self.shared.notify.notified().await;
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//!Local testing
+//!This tests the snapshot channel
+
+use std::time::Duration;
+
+use crate::emit::snapshot_channel;
+use crate::snapshot::OrderBookSnapshot;
+
+fn snapshot_with_bid(price: f64) -> OrderBookSnapshot {
+    OrderBookSnapshot { bids: vec![(price, 1)], ..Default::default() }
+}
+
+///A slow consumer on a capacity-1 channel only ever sees the newest snapshot
+#[tokio::test]
+async fn slow_consumer_receives_newest_snapshot() {
+    let (tx, mut rx) = snapshot_channel(1);
+
+    let consumer = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        rx.recv().await
+    });
+
+    let mut dropped = 0;
+    for price in 1..=5 {
+        if tx.try_send(snapshot_with_bid(price as f64)) {
+            dropped += 1;
+        }
+    }
+
+    assert_eq!(dropped, 4);
+    assert_eq!(consumer.await.unwrap(), Some(snapshot_with_bid(5.0)));
+}
+
+///The receiver drains queued snapshots before reporting the channel closed
+#[tokio::test]
+async fn recv_returns_none_after_sender_dropped() {
+    let (tx, mut rx) = snapshot_channel(2);
+    tx.try_send(snapshot_with_bid(1.0));
+    drop(tx);
+
+    assert_eq!(rx.recv().await, Some(snapshot_with_bid(1.0)));
+    assert_eq!(rx.recv().await, None);
+}
@@ -0,0 +1,52 @@
+use std::error::Error;
+use std::fmt;
+
+/// Everything that can go wrong while tracking a KuCoin order book.
+#[derive(Debug)]
+pub enum FeedError {
+    /// The WebSocket token could not be fetched or the bullet response was malformed.
+    TokenFetch(String),
+    /// The WebSocket connection could not be established.
+    WsConnect(String),
+    /// The subscription request could not be sent or was refused.
+    Subscribe(String),
+    /// A REST depth snapshot could not be fetched.
+    Snapshot(String),
+    /// The list of active contracts could not be fetched.
+    Contracts(String),
+    /// A message could not be parsed.
+    Parse(String),
+    /// A symbol contained characters other than ASCII letters, digits and `-`.
+    InvalidSymbol(String),
+    /// The connection was lost and every reconnect attempt failed.
+    ReconnectExhausted {
+        attempts: u32,
+        last: Box<FeedError>,
+    },
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedError::TokenFetch(msg) => write!(f, "failed to fetch WebSocket token: {}", msg),
+            FeedError::WsConnect(msg) => write!(f, "failed to connect to WebSocket: {}", msg),
+            FeedError::Subscribe(msg) => write!(f, "failed to subscribe: {}", msg),
+            FeedError::Snapshot(msg) => write!(f, "failed to fetch depth snapshot: {}", msg),
+            FeedError::Contracts(msg) => write!(f, "failed to fetch active contracts: {}", msg),
+            FeedError::Parse(msg) => write!(f, "failed to parse message: {}", msg),
+            FeedError::InvalidSymbol(symbol) => write!(f, "invalid symbol: {:?}", symbol),
+            FeedError::ReconnectExhausted { attempts, last } => {
+                write!(f, "gave up after {} reconnect attempts: {}", attempts, last)
+            }
+        }
+    }
+}
+
+impl Error for FeedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FeedError::ReconnectExhausted { last, .. } => Some(last.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::time::Duration;
+use futures_util::future::BoxFuture;
+use reqwest::Client;
+use serde_json::Value;
+use crate::api::{fetch_bullet, ping_message, subscribe_message, Channel};
+use crate::config::{DepthChannel, FeedConfig, Market};
+use crate::error::FeedError;
+use crate::order_book::{OrderBook, SequenceCheck, Side, UpdateReport};
+use crate::parse::{coerce_f64, parse_timestamp, SequenceRange};
+use crate::symbol::Symbol;
+
+/// A venue whose WebSocket order book feed can drive an [`OrderBook`].
+///
+/// See [`crate::api::start_exchange_listener`] for the listener running any of them.
+pub trait Exchange: Send + Sync {
+    /// A short, human-readable name of the venue.
+    fn name(&self) -> &'static str;
+
+    /// Resolves the WebSocket URL to connect to, including any token the venue requires.
+    ///
+    /// Called before every (re)connect, with `config` and `client` for venues that request the
+    /// URL over REST first.
+    fn ws_url<'a>(&'a self, config: &'a FeedConfig, client: &'a Client) -> BoxFuture<'a, Result<String, FeedError>>;
+
+    /// The keepalive frame sent every [`Exchange::ping_interval`].
+    fn ping_message(&self) -> String;
+
+    /// How often the venue expects a keepalive frame.
+    fn ping_interval(&self) -> Duration;
+
+    /// The subscribe request for the order book of `symbol`.
+    fn subscribe_message(&self, symbol: &Symbol) -> String;
+
+    /// Applies `msg` to `ob` if it is an order book update.
+    ///
+    /// Returns `None` for frames that carry no book data, such as acks and pongs.
+    fn apply_message(&self, ob: &mut OrderBook, msg: &Value) -> Option<UpdateReport>;
+}
+
+/// KuCoin's `level2Depth` snapshot feeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KuCoin {
+    pub market: Market,
+    pub depth_channel: DepthChannel,
+}
+
+impl Exchange for KuCoin {
+    fn name(&self) -> &'static str {
+        "KuCoin"
+    }
+
+    /// Requests a token from [`FeedConfig::token_url`] and returns the endpoint it was issued for.
+    fn ws_url<'a>(&'a self, config: &'a FeedConfig, client: &'a Client) -> BoxFuture<'a, Result<String, FeedError>> {
+        Box::pin(async move {
+            let bullet = fetch_bullet(config, client).await?;
+            Ok(format!("{}?token={}", bullet.endpoint, bullet.token))
+        })
+    }
+
+    fn ping_message(&self) -> String {
+        ping_message()
+    }
+
+    /// KuCoin's usual `pingInterval`; [`crate::api::start_websocket_listener`] follows the one
+    /// returned with each token instead.
+    fn ping_interval(&self) -> Duration {
+        Duration::from_secs(18)
+    }
+
+    fn subscribe_message(&self, symbol: &Symbol) -> String {
+        let mut config = FeedConfig::new(symbol.clone(), self.market);
+        config.set_depth_channel(self.depth_channel);
+        subscribe_message(&config.topic()).1
+    }
+
+    /// Applies `message` frames routed to the book by [`Channel::of`], as the listener does.
+    ///
+    /// Frames on other topics, such as trade executions, return `None` and leave the book as is.
+    fn apply_message(&self, ob: &mut OrderBook, msg: &Value) -> Option<UpdateReport> {
+        if Channel::of(msg) != Some(Channel::Book) {
+            return None;
+        }
+        Some(ob.apply_json(msg))
+    }
+}
+
+/// Bybit's v5 public linear `orderbook` feed.
+///
+/// Bybit quotes quantities in the base coin with fractional precision. They are stored in the
+/// book as whole lots of `1 / size_scale` coins, e.g. a `size_scale` of `100.0` keeps 0.01 ETH lots.
+///
+/// Snapshots keep all `depth` levels of the feed whatever the book's configured depth, since the
+/// deltas that follow update levels anywhere within them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bybit {
+    /// Levels per side of the subscribed feed: 1, 50, 200 or 500.
+    pub depth: usize,
+    /// Lots per unit of base coin.
+    pub size_scale: f64,
+    /// The WebSocket endpoint, [`Bybit::WS_URL`] by default.
+    pub endpoint: String,
+}
+
+impl Bybit {
+    /// The public WebSocket endpoint for USDT perpetuals.
+    pub const WS_URL: &'static str = "wss://stream.bybit.com/v5/public/linear";
+
+    /// The `orderbook` topic for `symbol`, e.g. `orderbook.50.ETHUSDT`.
+    pub fn topic(&self, symbol: &Symbol) -> String {
+        format!("orderbook.{}.{}", self.depth, symbol)
+    }
+
+    /// Reads `[price, qty]` string pairs, converting quantities to lots.
+    ///
+    /// A non-zero quantity smaller than half a lot is kept as one lot rather than rounded to zero,
+    /// which would remove a live level.
+    fn parse_levels(&self, levels: &Value, report: &mut UpdateReport) -> Vec<(f64, i64)> {
+        let mut parsed = vec![];
+
+        for level in levels.as_array().map(Vec::as_slice).unwrap_or_default() {
+            match (coerce_f64(&level[0]), coerce_f64(&level[1])) {
+                (Some(price), Some(qty)) if qty >= 0.0 => {
+                    let mut lots = (qty * self.size_scale).round() as i64;
+                    if lots == 0 && qty > 0.0 {
+                        lots = 1;
+                    }
+                    parsed.push((price, lots));
+                    report.accepted += 1;
+                }
+                _ => report.rejected += 1,
+            }
+        }
+        parsed
+    }
+}
+
+impl Default for Bybit {
+    fn default() -> Self {
+        Self { depth: 50, size_scale: 100.0, endpoint: Self::WS_URL.to_string() }
+    }
+}
+
+impl Exchange for Bybit {
+    fn name(&self) -> &'static str {
+        "Bybit"
+    }
+
+    fn ws_url<'a>(&'a self, _config: &'a FeedConfig, _client: &'a Client) -> BoxFuture<'a, Result<String, FeedError>> {
+        Box::pin(async move { Ok(self.endpoint.clone()) })
+    }
+
+    fn ping_message(&self) -> String {
+        serde_json::json!({"op": "ping"}).to_string()
+    }
+
+    /// Bybit drops connections that stay silent for more than 20 seconds.
+    fn ping_interval(&self) -> Duration {
+        Duration::from_secs(20)
+    }
+
+    fn subscribe_message(&self, symbol: &Symbol) -> String {
+        serde_json::json!({
+            "op": "subscribe",
+            "args": [self.topic(symbol)]
+        })
+        .to_string()
+    }
+
+    /// Applies a `snapshot` by replacing the book, and a `delta` level by level.
+    ///
+    /// The update id `data.u` is recorded as the book's last sequence and checked like KuCoin's
+    /// sequences, see [`UpdateReport::sequence`]. A snapshot, or a message with `u == 1` which Bybit
+    /// sends after restarting its service, resets the book and its numbering. Stale deltas, with an
+    /// id at or before the last one, are skipped; a [`SequenceCheck::Gap`] means updates were
+    /// missed and the caller should resubscribe for a fresh snapshot.
+    ///
+    /// # Example JSON Input
+    ///
+    /// ```json
+    /// {
+    ///   "topic": "orderbook.50.ETHUSDT",
+    ///   "type": "delta",
+    ///   "ts": 1687940967466,
+    ///   "data": {"s": "ETHUSDT", "b": [["1886.57", "0"]], "a": [["1886.89", "3.2"]], "u": 177400507, "seq": 66544703342}
+    /// }
+    /// ```
+    fn apply_message(&self, ob: &mut OrderBook, msg: &Value) -> Option<UpdateReport> {
+        if !msg["topic"].as_str().is_some_and(|topic| topic.starts_with("orderbook.")) {
+            return None;
+        }
+        let update_id = msg["data"]["u"].as_u64();
+        let is_snapshot = match msg["type"].as_str()? {
+            "snapshot" => true,
+            "delta" => update_id == Some(1),
+            _ => return None,
+        };
+
+        let mut report = UpdateReport::default();
+        let bids = self.parse_levels(&msg["data"]["b"], &mut report);
+        let asks = self.parse_levels(&msg["data"]["a"], &mut report);
+
+        if is_snapshot {
+            ob.set_last_sequence(None);
+        }
+        report.sequence = update_id.map(|id| ob.check_sequence(SequenceRange { start: id, end: id }));
+        match report.sequence {
+            Some(SequenceCheck::Stale) => {
+                report.accepted = 0;
+                return Some(report);
+            }
+            Some(SequenceCheck::Gap { expected, got }) => {
+                log::warn!("⚠️ Bybit update id gap: expected {}, got {}, resubscribe for a snapshot", expected, got);
+            }
+            _ => {}
+        }
+
+        if is_snapshot {
+            ob.replace_levels_keeping(bids, asks, self.depth);
+        } else {
+            for (price, size) in bids {
+                ob.set_level(Side::Bid, price, size);
+            }
+            for (price, size) in asks {
+                ob.set_level(Side::Ask, price, size);
+            }
+        }
+        if update_id.is_some() {
+            ob.set_last_sequence(update_id);
+        }
+        ob.after_update(parse_timestamp(msg));
+        Some(report)
+    }
+}
@@ -0,0 +1,175 @@
+//!Local testing
+//!This tests the exchange implementations
+
+use serde_json::{json, Value};
+
+use crate::config::{DepthChannel, FeedConfig, Market};
+use crate::exchange::{Bybit, Exchange, KuCoin};
+use crate::order_book::OrderBook;
+
+fn bybit_book() -> OrderBook {
+    OrderBook::with_config(&FeedConfig { depth: 50, ..FeedConfig::new("ETHUSDT".parse().unwrap(), Market::Futures) })
+}
+
+fn bybit_snapshot() -> Value {
+    json!({
+        "topic": "orderbook.50.ETHUSDT",
+        "type": "snapshot",
+        "ts": 1687940967466u64,
+        "data": {
+            "s": "ETHUSDT",
+            "b": [["1886.57", "1.25"], ["1886.50", "0.5"]],
+            "a": [["1886.89", "3.2"], ["1887.00", "10"]],
+            "u": 177400507,
+            "seq": 66544703342u64
+        }
+    })
+}
+
+#[test]
+fn bybit_subscribe_message() {
+    let msg: Value = serde_json::from_str(&Bybit::default().subscribe_message(&"ETHUSDT".parse().unwrap())).unwrap();
+    assert_eq!(msg, json!({"op": "subscribe", "args": ["orderbook.50.ETHUSDT"]}));
+    assert_eq!(Bybit::default().endpoint, "wss://stream.bybit.com/v5/public/linear");
+}
+
+#[test]
+fn bybit_snapshot_replaces_book() {
+    let mut ob = bybit_book();
+    ob.update(vec![(1.0, 1)], vec![(2.0, 1)]);
+
+    let report = Bybit::default().apply_message(&mut ob, &bybit_snapshot()).unwrap();
+
+    assert_eq!(report.accepted, 4);
+    let snapshot = ob.snapshot();
+    assert_eq!(snapshot.bids, vec![(1886.57, 125), (1886.5, 50)]);
+    assert_eq!(snapshot.asks, vec![(1886.89, 320), (1887.0, 1000)]);
+    assert_eq!(ob.timestamp(), Some(1687940967466));
+}
+
+#[test]
+fn bybit_delta_updates_levels() {
+    let mut ob = bybit_book();
+    let bybit = Bybit::default();
+    bybit.apply_message(&mut ob, &bybit_snapshot());
+
+    let delta = json!({
+        "topic": "orderbook.50.ETHUSDT",
+        "type": "delta",
+        "ts": 1687940967500u64,
+        "data": {
+            "s": "ETHUSDT",
+            "b": [["1886.57", "0"], ["1886.55", "2"]],
+            "a": [["1886.89", "1.1"]],
+            "u": 177400508,
+            "seq": 66544703343u64
+        }
+    });
+    bybit.apply_message(&mut ob, &delta).unwrap();
+
+    let snapshot = ob.snapshot();
+    assert_eq!(snapshot.bids, vec![(1886.55, 200), (1886.5, 50)]);
+    assert_eq!(snapshot.asks, vec![(1886.89, 110), (1887.0, 1000)]);
+}
+
+#[test]
+fn bybit_snapshot_and_deltas_agree_on_a_default_book() {
+    let mut ob = OrderBook::new();
+    let bybit = Bybit::default();
+    let bids: Vec<Value> = (0..50).map(|i| json!([format!("{}", 2000 - i), "1"])).collect();
+    let asks: Vec<Value> = (0..50).map(|i| json!([format!("{}", 2001 + i), "1"])).collect();
+    let snapshot = json!({"topic": "orderbook.50.ETHUSDT", "type": "snapshot", "data": {"b": bids, "a": asks, "u": 1}});
+    bybit.apply_message(&mut ob, &snapshot).unwrap();
+    assert_eq!(ob.snapshot().bids.len(), 50, "the whole feed depth is kept");
+
+    // A delta deep in the book updates an existing level rather than leaving holes before it.
+    let delta = json!({"topic": "orderbook.50.ETHUSDT", "type": "delta", "data": {"b": [["1970", "2"]], "a": [], "u": 2}});
+    bybit.apply_message(&mut ob, &delta).unwrap();
+
+    let snapshot = ob.snapshot();
+    assert_eq!(snapshot.bids.len(), 50);
+    assert_eq!(snapshot.bids[30], (1970.0, 200));
+    assert_eq!(snapshot.asks.len(), 50);
+}
+
+#[test]
+fn bybit_update_ids_skip_stale_deltas_and_reset_on_snapshots() {
+    use crate::order_book::SequenceCheck;
+
+    let mut ob = bybit_book();
+    let bybit = Bybit::default();
+    let delta = |u: u64, bid: &str| {
+        json!({"topic": "orderbook.50.ETHUSDT", "type": "delta", "data": {"b": [[bid, "1"]], "a": [], "u": u}})
+    };
+
+    let report = bybit.apply_message(&mut ob, &bybit_snapshot()).unwrap();
+    assert_eq!(report.sequence, Some(SequenceCheck::First));
+    assert_eq!(ob.last_sequence(), Some(177400507));
+
+    let report = bybit.apply_message(&mut ob, &delta(177400508, "1886.6")).unwrap();
+    assert_eq!(report.sequence, Some(SequenceCheck::Contiguous));
+
+    // A re-delivered delta is not applied.
+    let report = bybit.apply_message(&mut ob, &delta(177400508, "1886.7")).unwrap();
+    assert_eq!(report.sequence, Some(SequenceCheck::Stale));
+    assert_eq!(report.accepted, 0);
+    assert_eq!(ob.best_bid(), Some(1886.6));
+
+    let report = bybit.apply_message(&mut ob, &delta(177400510, "1886.8")).unwrap();
+    assert_eq!(report.sequence, Some(SequenceCheck::Gap { expected: 177400509, got: 177400510 }));
+
+    // After a service restart the numbering starts over at 1, and that message is a snapshot.
+    let report = bybit.apply_message(&mut ob, &delta(1, "1500.0")).unwrap();
+    assert_eq!(report.sequence, Some(SequenceCheck::First));
+    assert_eq!(ob.snapshot().bids, vec![(1500.0, 100)]);
+    assert!(ob.snapshot().asks.is_empty());
+    assert_eq!(bybit.apply_message(&mut ob, &delta(2, "1500.5")).unwrap().sequence, Some(SequenceCheck::Contiguous));
+}
+
+#[test]
+fn bybit_keeps_quantities_below_one_lot() {
+    let mut ob = bybit_book();
+    let bybit = Bybit::default();
+    bybit.apply_message(&mut ob, &bybit_snapshot());
+
+    let delta = json!({
+        "topic": "orderbook.50.ETHUSDT",
+        "type": "delta",
+        "ts": 1687940967500u64,
+        "data": {"s": "ETHUSDT", "b": [["1886.57", "0.004"]], "a": [["1887.50", "0.001"]], "u": 177400508}
+    });
+    bybit.apply_message(&mut ob, &delta).unwrap();
+
+    let snapshot = ob.snapshot();
+    assert_eq!(snapshot.bids, vec![(1886.57, 1), (1886.5, 50)], "a tiny size must not remove the level");
+    assert_eq!(snapshot.asks, vec![(1886.89, 320), (1887.0, 1000), (1887.5, 1)]);
+}
+
+#[test]
+fn bybit_ignores_non_book_frames() {
+    let mut ob = bybit_book();
+    let ack = json!({"success": true, "ret_msg": "", "op": "subscribe", "conn_id": "abc"});
+    assert_eq!(Bybit::default().apply_message(&mut ob, &ack), None);
+}
+
+#[test]
+fn kucoin_applies_depth_messages() {
+    let kucoin = KuCoin { market: Market::Futures, depth_channel: DepthChannel::Depth5 };
+    let mut ob = OrderBook::new();
+    let msg = json!({"type": "message", "data": {"bids": [["100.0", 1]], "asks": [["101.0", 2]]}});
+
+    assert_eq!(kucoin.apply_message(&mut ob, &msg).unwrap().accepted, 2);
+    assert_eq!(kucoin.apply_message(&mut ob, &json!({"type": "ack"})), None);
+
+    let trade = json!({
+        "type": "message",
+        "topic": "/contractMarket/execution:ETHUSDTM",
+        "data": {"side": "buy", "size": 3, "price": "100.5"}
+    });
+    assert_eq!(kucoin.apply_message(&mut ob, &trade), None);
+    assert_eq!(ob.snapshot().bids, vec![(100.0, 1)], "a trade frame must not clear the book");
+    assert_eq!(ob.snapshot().asks, vec![(101.0, 2)]);
+
+    let subscribe: Value = serde_json::from_str(&kucoin.subscribe_message(&"ETHUSDTM".parse().unwrap())).unwrap();
+    assert_eq!(subscribe["topic"], "/contractMarket/level2Depth5:ETHUSDTM");
+}
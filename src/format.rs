@@ -0,0 +1,193 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use crate::order_book::Side;
+
+/// Builds the line printed above the order book table.
+///
+/// # Arguments
+///
+/// * `now` - The wall-clock time the book is printed at.
+/// * `event_ts` - The event timestamp of the last applied message, in milliseconds.
+///
+/// # Examples
+///
+/// ```
+/// # use chrono::{TimeZone, Utc};
+/// # use warmup_project::format::header_line;
+/// let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+/// assert_eq!(header_line(now, None), "Current order book state @ 2024-01-01T12:00:00Z");
+/// ```
+pub fn header_line(now: DateTime<Utc>, event_ts: Option<i64>) -> String {
+    let mut line = format!(
+        "Current order book state @ {}",
+        now.to_rfc3339_opts(SecondsFormat::Secs, true)
+    );
+
+    if let Some(ts) = event_ts {
+        match DateTime::<Utc>::from_timestamp_millis(ts) {
+            Some(event) => line.push_str(&format!(
+                " (event ts: {})",
+                event.to_rfc3339_opts(SecondsFormat::Millis, true)
+            )),
+            None => line.push_str(&format!(" (event ts: {})", ts)),
+        }
+    }
+    line
+}
+
+/// Builds the single-line best bid/offer summary used by the compact output format.
+///
+/// Prices are shown as received and the spread is rounded to the finer of their precisions.
+/// A missing side is shown as `-`.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::format::compact_line;
+/// assert_eq!(compact_line(Some(1999.99), Some(2000.01)), "BBO 1999.99/2000.01 spread 0.02");
+/// assert_eq!(compact_line(Some(1999.99), None), "BBO 1999.99/- spread -");
+/// ```
+pub fn compact_line(best_bid: Option<f64>, best_ask: Option<f64>) -> String {
+    let show = |price: Option<f64>| price.map_or("-".to_string(), |p| p.to_string());
+
+    let spread = match (best_bid, best_ask) {
+        (Some(bid), Some(ask)) => {
+            let precision = decimals(bid).max(decimals(ask));
+            format!("{:.*}", precision, ask - bid)
+        }
+        _ => "-".to_string(),
+    };
+    format!("BBO {}/{} spread {}", show(best_bid), show(best_ask), spread)
+}
+
+/// Builds the ticker line of the ticker output format: best bid, best ask, spread and mid.
+///
+/// Prices are shown as received. The spread is rounded to the finer of their precisions and
+/// the mid to one more decimal, since it can fall halfway between two ticks. Anything that needs
+/// a missing side is shown as `-`.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::format::ticker_line;
+/// assert_eq!(
+///     ticker_line(Some(1999.99), Some(2000.01)),
+///     "bid 1999.99 ask 2000.01 spread 0.02 mid 2000.000"
+/// );
+/// assert_eq!(ticker_line(None, Some(2000.01)), "bid - ask 2000.01 spread - mid -");
+/// ```
+pub fn ticker_line(best_bid: Option<f64>, best_ask: Option<f64>) -> String {
+    let show = |price: Option<f64>| price.map_or("-".to_string(), |p| p.to_string());
+
+    let (spread, mid) = match (best_bid, best_ask) {
+        (Some(bid), Some(ask)) => {
+            let precision = decimals(bid).max(decimals(ask));
+            (
+                format!("{:.*}", precision, ask - bid),
+                format!("{:.*}", precision + 1, (bid + ask) / 2.0),
+            )
+        }
+        _ => ("-".to_string(), "-".to_string()),
+    };
+    format!("bid {} ask {} spread {} mid {}", show(best_bid), show(best_ask), spread, mid)
+}
+
+/// Rewrites `line` for in-place display: a carriage return moves back to the start of the line
+/// and the rest of the previous, possibly longer, line is cleared. No newline is added.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::format::in_place;
+/// assert_eq!(in_place("bid 1 ask 2\n"), "\rbid 1 ask 2\x1b[K");
+/// ```
+pub fn in_place(line: &str) -> String {
+    format!("\r{}\x1b[K", line.trim_end_matches('\n'))
+}
+
+/// The prettytable style spec of a level's cells in the printed table.
+///
+/// Bids are green and asks red. Levels whose size exceeds `wall_threshold` are walls and are shown
+/// in bold yellow instead (`b` is bold, while `B` would set a background colour).
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::format::level_style;
+/// # use warmup_project::order_book::Side;
+/// assert_eq!(level_style(Side::Bid, 10, Some(500)), "Fg");
+/// assert_eq!(level_style(Side::Bid, 900, Some(500)), "Fyb");
+/// ```
+pub fn level_style(side: Side, size: i64, wall_threshold: Option<i64>) -> &'static str {
+    let is_wall = wall_threshold.is_some_and(|threshold| size > threshold);
+    match (side, is_wall) {
+        (_, true) => "Fyb",
+        (Side::Bid, false) => "Fg",
+        (Side::Ask, false) => "Fr",
+    }
+}
+
+/// The number of digits after the decimal point in the shortest representation of `value`.
+pub(crate) fn decimals(value: f64) -> usize {
+    let text = value.to_string();
+    text.find('.').map_or(0, |dot| text.len() - dot - 1)
+}
+
+/// Formats `values` with a common number of decimals, right-aligned to a common width.
+///
+/// Used for the size column of the order book table, so sizes with differing precision such as
+/// `1.5` and `100.25` line up on the decimal point.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::format::align_decimals;
+/// assert_eq!(align_decimals(&[1.5, 100.25]), vec!["  1.50", "100.25"]);
+/// assert_eq!(align_decimals(&[7.0, 1200.0]), vec!["   7", "1200"]);
+/// ```
+pub fn align_decimals(values: &[f64]) -> Vec<String> {
+    let precision = values.iter().map(|&value| decimals(value)).max().unwrap_or(0);
+    let cells: Vec<String> = values.iter().map(|value| format!("{:.*}", precision, value)).collect();
+    let width = cells.iter().map(|cell| cell.chars().count()).max().unwrap_or(0);
+
+    cells.into_iter().map(|cell| format!("{:>1$}", cell, width)).collect()
+}
+
+/// Lays out `rows` as plain text with every column padded to its widest cell.
+///
+/// This is the dependency-free fallback used by [`crate::order_book::OrderBook::render_table`]
+/// when the `pretty` feature is disabled.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::format::plain_table;
+/// let rows = vec![
+///     vec!["Type".to_string(), "Price".to_string()],
+///     vec!["Bids".to_string(), "2000.5".to_string()],
+/// ];
+/// assert_eq!(plain_table(&rows), "Type | Price \nBids | 2000.5\n");
+/// ```
+pub fn plain_table(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|col| {
+            rows.iter()
+                .filter_map(|row| row.get(col))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut out = String::new();
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        out.push_str(&cells.join(" | "));
+        out.push('\n');
+    }
+    out
+}
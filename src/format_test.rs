@@ -0,0 +1,74 @@
+//!Local testing
+//!This tests the plain-text table formatter
+
+use crate::format::plain_table;
+
+fn row(cells: &[&str]) -> Vec<String> {
+    cells.iter().map(|cell| cell.to_string()).collect()
+}
+
+#[test]
+fn plain_table_aligns_columns() {
+    let rows = vec![
+        row(&["Type", "Symbol", "Price", "Contract size"]),
+        row(&["Bids", "ETHUSDTM", "2677.99", "197"]),
+        row(&["Asks", "ETHUSDTM", "2678.5", "34"]),
+    ];
+
+    let table = plain_table(&rows);
+    let lines: Vec<&str> = table.lines().collect();
+
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[1], "Bids | ETHUSDTM | 2677.99 | 197          ");
+    for line in &lines {
+        assert_eq!(line.len(), lines[0].len());
+    }
+    let separators: Vec<Vec<usize>> = lines
+        .iter()
+        .map(|line| line.match_indices('|').map(|(i, _)| i).collect())
+        .collect();
+    assert!(separators.iter().all(|s| *s == separators[0]));
+}
+
+#[test]
+fn header_line_includes_clock_and_event_timestamp() {
+    use chrono::{TimeZone, Utc};
+    use crate::format::header_line;
+
+    let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+
+    assert_eq!(header_line(now, None), "Current order book state @ 2024-01-01T12:00:00Z");
+    assert_eq!(
+        header_line(now, Some(1704110399950)),
+        "Current order book state @ 2024-01-01T12:00:00Z (event ts: 2024-01-01T11:59:59.950Z)"
+    );
+}
+
+#[test]
+fn level_style_highlights_walls() {
+    use crate::format::level_style;
+    use crate::order_book::Side;
+
+    assert_eq!(level_style(Side::Bid, 1000, Some(500)), "Fyb");
+    assert_eq!(level_style(Side::Ask, 501, Some(500)), "Fyb");
+
+    assert_eq!(level_style(Side::Bid, 500, Some(500)), "Fg");
+    assert_eq!(level_style(Side::Ask, 20, Some(500)), "Fr");
+    assert_eq!(level_style(Side::Ask, 1_000_000, None), "Fr");
+}
+
+#[test]
+fn align_decimals_lines_up_fractional_sizes() {
+    use crate::format::align_decimals;
+
+    let sizes = align_decimals(&[1.5, 100.25]);
+    assert_eq!(sizes, vec!["  1.50", "100.25"]);
+
+    let rows = vec![
+        row(&["Bids", "2000.5", &sizes[0]]),
+        row(&["Asks", "2001", &sizes[1]]),
+    ];
+    let table = plain_table(&rows);
+    let dots: Vec<Option<usize>> = table.lines().map(|line| line.rfind('.')).collect();
+    assert_eq!(dots[0], dots[1]);
+}
@@ -0,0 +1,119 @@
+//! Streams snapshots to local processes, such as a separate UI, over a Unix domain socket.
+//!
+//! Every snapshot is written as one frame: its JSON (see [`OrderBookSnapshot::to_json`]) prefixed
+//! with the byte length of the JSON as a big-endian `u32`.
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use crate::emit::SnapshotReceiver;
+use crate::snapshot::OrderBookSnapshot;
+
+/// An encoded frame, shared by the queues of every client.
+type Frame = Arc<[u8]>;
+
+/// Frames queued per client before it is considered too slow and disconnected.
+const CLIENT_QUEUE: usize = 64;
+/// How long writing a single frame to a client may take before it is disconnected.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serializes `snapshot` as a length-prefixed JSON frame.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::ipc::encode_frame;
+/// # use warmup_project::snapshot::OrderBookSnapshot;
+/// let frame = encode_frame(&OrderBookSnapshot::default());
+/// let len = u32::from_be_bytes(frame[..4].try_into().unwrap()) as usize;
+/// assert_eq!(len, frame.len() - 4);
+/// ```
+pub fn encode_frame(snapshot: &OrderBookSnapshot) -> Vec<u8> {
+    let json = snapshot.to_json().to_string();
+    let mut frame = Vec::with_capacity(4 + json.len());
+    frame.extend_from_slice(&(json.len() as u32).to_be_bytes());
+    frame.extend_from_slice(json.as_bytes());
+    frame
+}
+
+/// Listens on the Unix socket at `path` and writes every snapshot from `receiver` to each connected client.
+///
+/// A stale socket file left at `path` by a previous run is removed first. Each client is written
+/// to by its own task from a queue of [`CLIENT_QUEUE`] frames, so a client that stops reading
+/// never holds up the others: it is dropped once its queue fills up or a write takes longer than
+/// [`WRITE_TIMEOUT`]. Clients that disconnect are dropped too. Returns once the sending half of
+/// `receiver` is dropped and the queued frames have been written.
+///
+/// # Example Usage
+///
+/// ```no_run
+/// # use warmup_project::emit::snapshot_channel;
+/// # use warmup_project::ipc::serve_snapshots;
+/// # async fn run() {
+/// let (tx, rx) = snapshot_channel(64);
+/// tokio::spawn(serve_snapshots("/tmp/orderbook.sock", rx));
+/// // Pass `Some(tx)` as the sink of the WebSocket listener.
+/// # drop(tx);
+/// # }
+/// ```
+pub async fn serve_snapshots(path: impl AsRef<Path>, mut receiver: SnapshotReceiver) -> io::Result<()> {
+    let path = path.as_ref();
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    let mut clients: Vec<(mpsc::Sender<Frame>, JoinHandle<()>)> = vec![];
+
+    loop {
+        tokio::select! {
+            // Register waiting clients before forwarding, so they get the snapshot that follows
+            biased;
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                let (queue, frames) = mpsc::channel(CLIENT_QUEUE);
+                clients.push((queue, tokio::spawn(write_frames(stream, frames))));
+            }
+            snapshot = receiver.recv() => {
+                let Some(snapshot) = snapshot else { break };
+                let frame: Frame = encode_frame(&snapshot).into();
+
+                clients.retain(|(queue, task)| match queue.try_send(frame.clone()) {
+                    Ok(()) => true,
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        log::warn!("⚠️ IPC client fell {} frames behind, disconnecting it", CLIENT_QUEUE);
+                        task.abort();
+                        false
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => false,
+                });
+            }
+        }
+    }
+
+    // Closing the queues lets each writer finish the frames it holds, bounded by the write timeout
+    for (queue, task) in clients {
+        drop(queue);
+        let _ = task.await;
+    }
+    Ok(())
+}
+
+/// Writes queued frames to `stream` until the queue closes, the client disconnects or a write
+/// times out.
+async fn write_frames(mut stream: UnixStream, mut frames: mpsc::Receiver<Frame>) {
+    while let Some(frame) = frames.recv().await {
+        match tokio::time::timeout(WRITE_TIMEOUT, stream.write_all(&frame)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(_)) => return,
+            Err(_) => {
+                log::warn!("⚠️ IPC client did not read a frame within {:?}, disconnecting it", WRITE_TIMEOUT);
+                return;
+            }
+        }
+    }
+}
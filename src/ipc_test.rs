@@ -0,0 +1,96 @@
+//!Local testing
+//!This tests the Unix domain socket snapshot stream
+
+use serde_json::Value;
+use tokio::io::AsyncReadExt;
+use tokio::net::UnixStream;
+
+use crate::emit::snapshot_channel;
+use crate::ipc::serve_snapshots;
+use crate::snapshot::OrderBookSnapshot;
+
+#[tokio::test]
+async fn client_receives_length_prefixed_snapshot() {
+    let path = std::env::temp_dir().join(format!("orderbook-ipc-test-{}.sock", std::process::id()));
+    let (tx, rx) = snapshot_channel(8);
+    let server = tokio::spawn(serve_snapshots(path.clone(), rx));
+
+    let mut client = loop {
+        match UnixStream::connect(&path).await {
+            Ok(client) => break client,
+            Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+        }
+    };
+
+    let snapshot = OrderBookSnapshot {
+        symbol: "ETHUSDTM".to_string(),
+        bids: vec![(2000.0, 15)],
+        asks: vec![(2001.0, 3)],
+        ..Default::default()
+    };
+    tx.try_send(snapshot);
+
+    let mut len = [0; 4];
+    client.read_exact(&mut len).await.unwrap();
+    let mut body = vec![0; u32::from_be_bytes(len) as usize];
+    client.read_exact(&mut body).await.unwrap();
+
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["symbol"], "ETHUSDTM");
+    assert_eq!(json["bids"][0][0], 2000.0);
+
+    drop(tx);
+    server.await.unwrap().unwrap();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn stalled_client_does_not_block_others() {
+    use std::time::Duration;
+
+    let path = std::env::temp_dir().join(format!("orderbook-ipc-stall-{}.sock", std::process::id()));
+    let (tx, rx) = snapshot_channel(8);
+    let server = tokio::spawn(serve_snapshots(path.clone(), rx));
+
+    let connect = || async {
+        loop {
+            match UnixStream::connect(&path).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+    };
+    // Connected but never read from, so its socket buffer fills up.
+    let _stalled = connect().await;
+    let mut reader = connect().await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let levels: Vec<(f64, i64)> = (0..2_000).map(|i| (2000.0 - i as f64, 1)).collect();
+    let last_symbol = tokio::spawn(async move {
+        loop {
+            let mut len = [0; 4];
+            reader.read_exact(&mut len).await.unwrap();
+            let mut body = vec![0; u32::from_be_bytes(len) as usize];
+            reader.read_exact(&mut body).await.unwrap();
+            let json: Value = serde_json::from_slice(&body).unwrap();
+            if json["symbol"] == "LAST" {
+                break;
+            }
+        }
+    });
+
+    for i in 0..200 {
+        let symbol = if i == 199 { "LAST" } else { "ETHUSDTM" };
+        tx.try_send(OrderBookSnapshot { symbol: symbol.to_string(), bids: levels.clone(), ..Default::default() });
+        tokio::task::yield_now().await;
+    }
+
+    tokio::time::timeout(Duration::from_secs(3), last_symbol)
+        .await
+        .expect("the reading client was held up by the stalled one")
+        .unwrap();
+
+    drop(tx);
+    tokio::time::timeout(Duration::from_secs(3), server).await.unwrap().unwrap().unwrap();
+    let _ = std::fs::remove_file(&path);
+}
@@ -1,11 +1,29 @@
 use std::cmp::Ordering;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Item {
     pub price: f64,
     pub size: i64,
 }
 
+impl Item {
+    /// Creates a level, normalizing a price of `-0.0` to `0.0`.
+    ///
+    /// Levels are ordered with [`f64::total_cmp`], which tells `-0.0` and `0.0` apart, so without
+    /// this a zero price could be stored as two distinct levels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::item::Item;
+    /// assert_eq!(Item::new(-0.0, 5).cmp(&Item::new(0.0, 5)), std::cmp::Ordering::Equal);
+    /// ```
+    pub fn new(price: f64, size: i64) -> Self {
+        let price = if price == 0.0 { 0.0 } else { price };
+        Item { price, size }
+    }
+}
+
 impl Eq for Item {}
 
 impl Ord for Item {
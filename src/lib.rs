@@ -0,0 +1,55 @@
+pub mod api;
+pub mod callback;
+pub mod config;
+pub mod emit;
+pub mod error;
+pub mod exchange;
+pub mod format;
+#[cfg(unix)]
+pub mod ipc;
+pub mod item;
+pub mod logging;
+pub mod order_book;
+pub mod parse;
+pub mod proxy;
+#[cfg(feature = "redis")]
+pub mod pubsub;
+pub mod record;
+pub mod snapshot;
+pub mod stats;
+pub mod status;
+pub mod symbol;
+pub mod telemetry;
+
+#[cfg(test)]
+mod api_test;
+#[cfg(all(test, feature = "arrow"))]
+mod arrow_test;
+#[cfg(test)]
+mod config_test;
+#[cfg(test)]
+mod emit_test;
+#[cfg(test)]
+mod exchange_test;
+#[cfg(test)]
+mod format_test;
+#[cfg(all(test, unix))]
+mod ipc_test;
+#[cfg(test)]
+mod logging_test;
+#[cfg(test)]
+mod ob_test;
+#[cfg(test)]
+mod parse_test;
+#[cfg(test)]
+mod proxy_test;
+#[cfg(all(test, feature = "redis"))]
+mod pubsub_test;
+#[cfg(test)]
+mod record_test;
+#[cfg(test)]
+mod stats_test;
+#[cfg(test)]
+mod symbol_test;
+#[cfg(all(test, feature = "metrics"))]
+mod telemetry_test;
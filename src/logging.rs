@@ -0,0 +1,39 @@
+//! A minimal [`log`] backend writing every record to stderr, keeping stdout free for the order book.
+
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+/// Writes log records to stderr as `[LEVEL] message`.
+pub struct StderrLogger;
+
+static LOGGER: StderrLogger = StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the [`StderrLogger`] as the global logger, showing records up to `level`.
+///
+/// Fails if another logger was already installed.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::logging;
+/// logging::init(log::LevelFilter::Info).unwrap();
+/// log::info!("listening");
+/// ```
+pub fn init(level: LevelFilter) -> Result<(), SetLoggerError> {
+    log::set_logger(&LOGGER)?;
+    log::set_max_level(level);
+    Ok(())
+}
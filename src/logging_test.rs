@@ -0,0 +1,52 @@
+//!Local testing
+//!This tests that the log and print messages are clean UTF-8
+
+use std::fs;
+
+/// Emoji and symbols encoded as UTF-8 but decoded as Windows-1252 turn into these sequences:
+/// the lead bytes of emoji, of punctuation such as quotes, of symbols such as checkmarks and
+/// warning signs, and of the emoji variation selector. Written as escapes so this file does not
+/// match itself.
+const MOJIBAKE: [&str; 6] = [
+    "\u{FFFD}",
+    "\u{f0}\u{178}",
+    "\u{e2}\u{20ac}",
+    "\u{e2}\u{153}",
+    "\u{e2}\u{161}",
+    "\u{ef}\u{b8}",
+];
+
+fn is_garbled(line: &str) -> bool {
+    MOJIBAKE.iter().any(|sequence| line.contains(sequence))
+}
+
+#[test]
+fn sources_contain_no_mojibake() {
+    for entry in fs::read_dir("src").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+
+        let source = fs::read_to_string(&path).unwrap();
+        for (number, line) in source.lines().enumerate() {
+            assert!(
+                !is_garbled(line),
+                "{}:{} contains a garbled character: {}",
+                path.display(),
+                number + 1,
+                line
+            );
+        }
+    }
+}
+
+#[test]
+fn only_mojibake_sequences_are_flagged() {
+    assert!(is_garbled("log::warn!(\"\u{e2}\u{161}\u{a0}\u{ef}\u{b8}\u{8f} stale\")"));
+    assert!(is_garbled("\u{f0}\u{178}\u{201c}\u{b8} snapshot"));
+
+    // Accented letters and currency symbols on their own are valid text.
+    assert!(!is_garbled("â Ã ï ƒ € café naïve"));
+    assert!(!is_garbled("⚠️ 📸 ✅"));
+}
@@ -1,18 +1,115 @@
 use std::error::Error;
-use crate::order_book::OrderBook;
-use crate::api::start_websocket_listener;
-
-mod order_book;
-mod item;
-mod api;
+use warmup_project::config::{DepthChannel, FeedConfig, OutputFormat};
+use warmup_project::order_book::OrderBook;
+use warmup_project::api::{fetch_contract_multiplier, start_websocket_listener};
+use warmup_project::emit::{snapshot_channel, SnapshotSender};
+use warmup_project::logging;
+use warmup_project::record::record_snapshots;
+#[cfg(unix)]
+use warmup_project::ipc;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     
-    let mut ob = OrderBook::new();
+    logging::init(log::LevelFilter::Info)?;
+    let mut config = FeedConfig::default();
+
+    // `--output ndjson` prints one JSON snapshot per line, for piping into other tools
+    // `--output ticker --in-place` keeps a single best bid/ask line updated in place
+    // `--depth 50` subscribes to the 50-level depth channel instead of the 5-level one
+    // `--ipc-socket <path>` streams length-prefixed JSON snapshots over a Unix socket
+    // `--record <path> [--record-every <n>]` records every nth snapshot as JSON lines, gzipped for `.gz`
+    let mut ipc_socket = None;
+    let mut record_path = None;
+    let mut record_every = 1;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" => {
+                let value = args.next().ok_or("--output requires a value")?;
+                config.print.format = value.parse::<OutputFormat>()?;
+            }
+            "--in-place" => config.print.in_place = true,
+            "--depth" => {
+                let value = args.next().ok_or("--depth requires a value")?;
+                config.set_depth_channel(value.parse::<DepthChannel>()?);
+            }
+            "--ipc-socket" => {
+                ipc_socket = Some(args.next().ok_or("--ipc-socket requires a path")?);
+            }
+            "--record" => {
+                record_path = Some(args.next().ok_or("--record requires a path")?);
+            }
+            "--record-every" => {
+                let value = args.next().ok_or("--record-every requires a value")?;
+                record_every = value.parse::<usize>()?;
+            }
+            other => return Err(format!("unknown argument `{}`", other).into()),
+        }
+    }
+    if matches!(config.print.format, OutputFormat::Json | OutputFormat::Ticker) {
+        config.print.raw_messages = false;
+    }
+
+    // Use the exchange's multiplier for base-currency conversions, falling back to the built-in table
+    match fetch_contract_multiplier(&config, None).await {
+        Ok(multiplier) => config.apply_multiplier(multiplier),
+        Err(err) => log::warn!("⚠️ Using the default multiplier: {}", err),
+    }
+
+    let mut ob = OrderBook::with_config(&config);
+
+    let mut sinks: Vec<SnapshotSender> = vec![];
+    match ipc_socket {
+        #[cfg(unix)]
+        Some(path) => {
+            let (tx, rx) = snapshot_channel(64);
+            tokio::spawn(async move {
+                if let Err(err) = ipc::serve_snapshots(path, rx).await {
+                    log::error!("❌ IPC socket error: {}", err);
+                }
+            });
+            sinks.push(tx);
+        }
+        #[cfg(not(unix))]
+        Some(_) => return Err("--ipc-socket is only supported on Unix".into()),
+        None => {}
+    }
+    let recording = record_path.map(|path| {
+        let (tx, rx) = snapshot_channel(1024);
+        sinks.push(tx);
+        tokio::spawn(async move {
+            if let Err(err) = record_snapshots(path, record_every, rx).await {
+                log::error!("❌ Recording error: {}", err);
+            }
+        })
+    });
+
+    // A single consumer is fed directly, several through a task copying each snapshot to all of them
+    let sink = match sinks.len() {
+        0 => None,
+        1 => sinks.pop(),
+        _ => {
+            let (tx, mut rx) = snapshot_channel(64);
+            tokio::spawn(async move {
+                while let Some(snapshot) = rx.recv().await {
+                    for sink in &sinks {
+                        sink.try_send(snapshot.clone());
+                    }
+                }
+            });
+            Some(tx)
+        }
+    };
     
     // Start WebSocket listener for live updates
-    start_websocket_listener(&mut ob).await?;
+    let result = start_websocket_listener(&mut ob, &config, None, sink).await;
+
+    // The listener dropped its sink, so the recording can write its last snapshot and complete
+    if let Some(recording) = recording {
+        let _ = recording.await;
+    }
+    result?;
 
     Ok(())
 }
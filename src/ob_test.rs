@@ -1,14 +1,16 @@
-///Local testing
-///This tests the parsing and the OrderBook
+//!Local testing
+//!This tests the parsing and the OrderBook
 
 use serde_json::Value;
 use std::fs;
 use std::path::Path;
 
 use crate::order_book::OrderBook;  // Import everything from `order_book`
-use crate::item::Item;
+use crate::config::OutputFormat;
+use crate::parse::{coerce_f64, coerce_i64};
 
-pub fn ob_test() -> Result<(), Box<dyn std::error::Error>> {
+#[test]
+fn ob_test() -> Result<(), Box<dyn std::error::Error>> {
     println!("Path");
     let path = Path::new("src/test.txt");
 
@@ -28,12 +30,9 @@ pub fn ob_test() -> Result<(), Box<dyn std::error::Error>> {
     if let Some(bid) = json_data["data"]["bids"].as_array() {
         println!("Top 5 Bids:");
         for bid in bid.iter().take(5) {
-            let price = bid[0].as_f64().unwrap_or(bid[0].as_str()
-            .and_then(|s| s.parse::<f64>().ok()) // Try parsing it
-            .unwrap_or(0.0));
-            let size = bid[1].as_i64().unwrap_or(bid[1].as_str()
-            .and_then(|s| s.parse::<i64>().ok()) // Try parsing it
-            .unwrap_or(0));
+            let (Some(price), Some(size)) = (coerce_f64(&bid[0]), coerce_i64(&bid[1])) else {
+                continue;
+            };
             println!("  Price: {}, Size: {}", &price, &size);
             bids.push((price, size));
         }
@@ -42,12 +41,9 @@ pub fn ob_test() -> Result<(), Box<dyn std::error::Error>> {
     if let Some(ask) = json_data["data"]["asks"].as_array() {
         println!("Top 5 Asks:");
         for ask in ask.iter().take(5) {
-            let price = ask[0].as_f64().unwrap_or(ask[0].as_str()
-            .and_then(|s| s.parse::<f64>().ok()) // Try parsing it
-            .unwrap_or(0.0));
-            let size = ask[1].as_i64().unwrap_or(ask[1].as_str()
-            .and_then(|s| s.parse::<i64>().ok()) // Try parsing it
-            .unwrap_or(0));
+            let (Some(price), Some(size)) = (coerce_f64(&ask[0]), coerce_i64(&ask[1])) else {
+                continue;
+            };
             println!("  Price: {}, Size: {}", &price, &size);
             asks.push((price, size));
         }
@@ -59,3 +55,1142 @@ pub fn ob_test() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn cumulative_accumulates_from_best_price() {
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    ob.update(
+        vec![(99.0, 3), (98.0, 2), (97.0, 5)],
+        vec![(101.0, 1), (102.0, 4), (103.0, 2)],
+    );
+
+    let bids = ob.cumulative(Side::Bid);
+    let asks = ob.cumulative(Side::Ask);
+
+    assert_eq!(bids, vec![(99.0, 3), (98.0, 5), (97.0, 10)]);
+    assert_eq!(asks, vec![(101.0, 1), (102.0, 5), (103.0, 7)]);
+    assert!(bids.windows(2).all(|w| w[0].1 < w[1].1));
+    assert!(asks.windows(2).all(|w| w[0].1 < w[1].1));
+}
+
+#[test]
+fn apply_json_updates_book_and_reports() {
+    let contents = fs::read_to_string("src/test.txt").unwrap();
+    let json_data: Value = serde_json::from_str(&contents).unwrap();
+
+    let mut ob = OrderBook::new();
+    let report = ob.apply_json(&json_data);
+
+    assert_eq!(report.accepted, 10);
+    assert_eq!(report.rejected, 0);
+    let snapshot = ob.snapshot();
+    assert_eq!(snapshot.bids[0], (2678.35, 12));
+    assert_eq!(snapshot.asks[0], (2678.36, 4356));
+    assert_eq!(snapshot.bids.len(), 5);
+    assert_eq!(snapshot.asks.len(), 5);
+    assert_eq!(ob.timestamp(), Some(1739942942550));
+}
+
+#[test]
+fn apply_json_rejects_malformed_levels() {
+    let json_data: Value = serde_json::json!({
+        "data": {"bids": [["100.0", 1], "garbage"], "asks": [[101.0]]}
+    });
+
+    let mut ob = OrderBook::new();
+    let report = ob.apply_json(&json_data);
+
+    assert_eq!(report.accepted, 1);
+    assert_eq!(report.rejected, 2);
+    assert_eq!(ob.snapshot().bids, vec![(100.0, 1)]);
+    assert!(ob.snapshot().asks.is_empty());
+}
+
+#[test]
+fn apply_json_rejects_negative_sizes() {
+    let json_data: Value = serde_json::json!({
+        "data": {"bids": [["100.0", "-5"], ["99.0", 2]], "asks": [["101.0", -1]]}
+    });
+
+    let mut ob = OrderBook::new();
+    let report = ob.apply_json(&json_data);
+
+    assert_eq!(report.accepted, 1);
+    assert_eq!(report.rejected, 2);
+    assert_eq!(ob.snapshot().bids, vec![(99.0, 2)]);
+    assert!(ob.snapshot().asks.is_empty());
+}
+
+#[test]
+fn zero_size_levels_are_not_stored() {
+    let json_data: Value = serde_json::json!({
+        "data": {"bids": [["100.0", 0], ["99.0", 2]], "asks": []}
+    });
+
+    let mut ob = OrderBook::new();
+    let report = ob.apply_json(&json_data);
+
+    assert_eq!(report.rejected, 0);
+    assert_eq!(ob.snapshot().bids, vec![(99.0, 2)]);
+}
+
+///Compares the sorted-vec sides against the previous `BTreeSet` implementation on pseudo-random inputs
+#[test]
+fn slices_match_btreeset_reference() {
+    use std::collections::BTreeSet;
+    use std::time::Instant;
+    use crate::item::Item;
+
+    let mut seed: u64 = 42;
+    let mut next = move |modulo: u64| {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) % modulo
+    };
+
+    let mut ob = OrderBook::new();
+    let started = Instant::now();
+    for _ in 0..1000 {
+        let bids: Vec<(f64, i64)> = (0..8).map(|_| (100.0 - next(10) as f64, 1 + next(50) as i64)).collect();
+        let asks: Vec<(f64, i64)> = (0..8).map(|_| (101.0 + next(10) as f64, 1 + next(50) as i64)).collect();
+
+        let mut ref_bids: BTreeSet<Item> = BTreeSet::new();
+        let mut ref_asks: BTreeSet<Item> = BTreeSet::new();
+        for &(price, size) in &bids {
+            ref_bids.insert(Item { price, size });
+        }
+        for &(price, size) in &asks {
+            ref_asks.insert(Item { price, size });
+        }
+        while ref_bids.len() > ob.depth() {
+            ref_bids.pop_first();
+        }
+        while ref_asks.len() > ob.depth() {
+            ref_asks.pop_last();
+        }
+
+        ob.update(bids, asks);
+
+        assert!(ob.bids_slice().iter().eq(ref_bids.iter()));
+        assert!(ob.asks_slice().iter().eq(ref_asks.iter()));
+        assert!(ob.bids_slice().windows(2).all(|w| w[0].price < w[1].price));
+        assert!(ob.asks_slice().windows(2).all(|w| w[0].price < w[1].price));
+    }
+    println!("1000 updates in {:?}", started.elapsed());
+}
+
+#[test]
+fn top_size_velocity_uses_timestamps() {
+    let mut ob = OrderBook::new();
+    let msg = |ts: i64, bid_size: i64, ask_size: i64| {
+        serde_json::json!({
+            "data": {"bids": [["100.0", bid_size]], "asks": [["101.0", ask_size]], "timestamp": ts}
+        })
+    };
+
+    ob.apply_json(&msg(1_000, 10, 40));
+    assert_eq!(ob.top_size_velocity(), None);
+
+    ob.apply_json(&msg(1_500, 30, 20));
+    assert_eq!(ob.top_size_velocity(), Some((40.0, -40.0)));
+}
+
+#[test]
+fn sequence_ranges_are_checked_against_previous_end() {
+    use crate::order_book::SequenceCheck;
+
+    let msg = |start: u64, end: u64| {
+        serde_json::json!({
+            "data": {"bids": [["100.0", 1]], "asks": [["101.0", 1]], "sequenceStart": start, "sequenceEnd": end}
+        })
+    };
+
+    let mut ob = OrderBook::new();
+    assert_eq!(ob.apply_json(&msg(10, 12)).sequence, Some(SequenceCheck::First));
+    assert_eq!(ob.last_sequence(), Some(12));
+    assert_eq!(ob.apply_json(&msg(13, 15)).sequence, Some(SequenceCheck::Contiguous));
+    assert_eq!(
+        ob.apply_json(&msg(18, 20)).sequence,
+        Some(SequenceCheck::Gap { expected: 16, got: 18 })
+    );
+    let stale = ob.apply_json(&serde_json::json!({
+        "data": {"bids": [["90.0", 1]], "asks": [], "sequenceStart": 17, "sequenceEnd": 19}
+    }));
+    assert_eq!(stale.sequence, Some(SequenceCheck::Stale));
+    assert_eq!(stale.accepted, 0);
+    assert_eq!(ob.last_sequence(), Some(20));
+    assert_eq!(ob.snapshot().bids, vec![(100.0, 1)]);
+}
+
+#[test]
+fn single_sequence_field_is_a_one_message_range() {
+    use crate::parse::{parse_sequence, SequenceRange};
+
+    let data = serde_json::json!({"sequence": 1708804089297u64});
+    assert_eq!(
+        parse_sequence(&data),
+        Some(SequenceRange { start: 1708804089297, end: 1708804089297 })
+    );
+    assert_eq!(parse_sequence(&serde_json::json!({})), None);
+}
+
+#[test]
+fn from_depth20_loads_all_levels() {
+    let contents = fs::read_to_string("src/depth20.txt").unwrap();
+    let json_data: Value = serde_json::from_str(&contents).unwrap();
+
+    let ob = OrderBook::from_depth20(&json_data);
+    let snapshot = ob.snapshot();
+
+    assert_eq!(ob.depth(), 20);
+    assert_eq!(ob.symbol(), "ETHUSDTM");
+    assert_eq!(snapshot.bids.len(), 20);
+    assert_eq!(snapshot.asks.len(), 20);
+    assert_eq!(snapshot.bids[0], (2678.35, 10));
+    assert_eq!(snapshot.asks[0], (2678.36, 15));
+    assert_eq!(snapshot.asks[19], (2679.31, 224));
+}
+
+#[test]
+fn price_transform_applies_before_insertion() {
+    let mut ob = OrderBook::new();
+    ob.set_price_transform(Box::new(|price| price * 2.0));
+    ob.update(vec![(99.0, 3), (100.0, 1), (98.5, 2)], vec![(101.0, 1), (100.5, 4)]);
+
+    let snapshot = ob.snapshot();
+    assert_eq!(snapshot.bids, vec![(200.0, 1), (198.0, 3), (197.0, 2)]);
+    assert_eq!(snapshot.asks, vec![(201.0, 4), (202.0, 1)]);
+    assert!(ob.bids_slice().windows(2).all(|w| w[0].price < w[1].price));
+}
+
+#[test]
+fn spread_history_is_capped_and_ordered() {
+    use crate::config::FeedConfig;
+
+    let config = FeedConfig { spread_history: 3, ..FeedConfig::default() };
+    let mut ob = OrderBook::with_config(&config);
+
+    for i in 1..=5 {
+        let msg = serde_json::json!({
+            "data": {"bids": [["100.0", 1]], "asks": [[100.0 + i as f64, 1]], "timestamp": i * 1000}
+        });
+        ob.apply_json(&msg);
+    }
+    ob.update(vec![(100.0, 1)], vec![]);
+
+    assert_eq!(ob.peek_spread_history(), &[(3000, 3.0), (4000, 4.0), (5000, 5.0)]);
+}
+
+///A level that fails to parse must not show up as a phantom `0.0` level
+#[test]
+fn unparseable_levels_are_skipped() {
+    let json_data: Value = serde_json::json!({
+        "data": {"bids": [["2678.35", 12], ["n/a", "12"]], "asks": [["2678.36", "lots"], ["2678.37", 4]]}
+    });
+
+    let mut ob = OrderBook::new();
+    let report = ob.apply_json(&json_data);
+
+    assert_eq!(report.accepted, 2);
+    assert_eq!(report.rejected, 2);
+    assert_eq!(ob.snapshot().bids, vec![(2678.35, 12)]);
+    assert_eq!(ob.snapshot().asks, vec![(2678.37, 4)]);
+
+    ob.update(vec![(f64::NAN, 5), (2678.0, 1)], vec![]);
+    assert_eq!(ob.snapshot().bids, vec![(2678.0, 1)]);
+}
+
+fn sample_book() -> OrderBook {
+    let mut ob = OrderBook::new();
+    ob.update(vec![(1999.99, 3), (1999.5, 7)], vec![(2000.01, 2), (2000.5, 4)]);
+    ob
+}
+
+#[test]
+fn render_table_format() {
+    let out = sample_book().render(OutputFormat::Table);
+
+    assert!(out.starts_with("Current order book state @ "));
+    assert!(out.contains("Contract size"));
+    assert!(out.contains("1999.99"));
+    assert!(out.contains("2000.5"));
+}
+
+#[test]
+fn render_json_format() {
+    let out = sample_book().render(OutputFormat::Json);
+    let value: Value = serde_json::from_str(out.trim_end()).unwrap();
+
+    assert_eq!(value["symbol"], "ETHUSDTM");
+    assert_eq!(value["bids"][0][0], 1999.99);
+    assert_eq!(value["asks"][1][1], 4);
+}
+
+#[test]
+fn render_compact_format() {
+    assert_eq!(sample_book().render(OutputFormat::Compact), "BBO 1999.99/2000.01 spread 0.02\n");
+}
+
+#[test]
+fn render_ticker_format() {
+    use crate::format::in_place;
+
+    let line = sample_book().render(OutputFormat::Ticker);
+    assert_eq!(line, "bid 1999.99 ask 2000.01 spread 0.02 mid 2000.000\n");
+    assert_eq!(in_place(&line), "\rbid 1999.99 ask 2000.01 spread 0.02 mid 2000.000\x1b[K");
+    assert_eq!(OrderBook::new().render(OutputFormat::Ticker), "bid - ask - spread - mid -\n");
+}
+
+#[test]
+fn execution_message_updates_last_trade() {
+    let mut ob = OrderBook::new();
+    assert_eq!(ob.last_trade(), None);
+
+    let execution = serde_json::json!({
+        "topic": "/contractMarket/execution:ETHUSDTM",
+        "type": "message",
+        "subject": "match",
+        "data": {"symbol": "ETHUSDTM", "side": "buy", "size": 3, "price": "2678.36", "ts": 1739942942550000000i64}
+    });
+    assert!(ob.apply_execution(&execution));
+    assert_eq!(ob.last_trade(), Some(2678.36));
+
+    assert!(!ob.apply_execution(&serde_json::json!({"data": {}})));
+    assert_eq!(ob.last_trade(), Some(2678.36));
+}
+
+#[test]
+fn deltas_never_exceed_max_levels() {
+    use crate::config::FeedConfig;
+    use crate::order_book::Side;
+
+    let config = FeedConfig { max_levels: 10, ..FeedConfig::default() };
+    let mut ob = OrderBook::with_config(&config);
+
+    for i in 0..25 {
+        ob.apply_delta(Side::Bid, 100.0 - i as f64, 1);
+        ob.apply_delta(Side::Ask, 101.0 + i as f64, 1);
+        assert!(ob.bids_slice().len() <= 10);
+        assert!(ob.asks_slice().len() <= 10);
+    }
+
+    assert_eq!(ob.bids_slice().len(), 10);
+    assert_eq!(ob.best_bid(), Some(100.0));
+    assert_eq!(ob.bids_slice()[0].price, 91.0);
+    assert_eq!(ob.best_ask(), Some(101.0));
+    assert_eq!(ob.asks_slice()[9].price, 110.0);
+}
+
+#[test]
+fn concentration_weights_size_by_distance_from_mid() {
+    let mut ob = OrderBook::new();
+    assert_eq!(ob.concentration(), None);
+
+    // mid = 100.0
+    ob.update(vec![(99.0, 10), (97.0, 8)], vec![(101.0, 6), (103.0, 4)]);
+    // 10/2 + 8/4 + 6/2 + 4/4 = 5 + 2 + 3 + 1
+    assert_eq!(ob.concentration(), Some(11.0));
+}
+
+#[test]
+fn is_balanced_within_tolerance() {
+    let mut ob = OrderBook::new();
+    ob.update(vec![(99.0, 52), (98.0, 0)], vec![(101.0, 48)]);
+
+    assert_eq!(ob.imbalance(), Some(0.04));
+    assert!(ob.is_balanced(0.05));
+}
+
+#[test]
+fn is_balanced_rejects_skewed_book() {
+    let mut ob = OrderBook::new();
+    ob.update(vec![(99.0, 10)], vec![(101.0, 30), (102.0, 10)]);
+
+    assert_eq!(ob.imbalance(), Some(-0.6));
+    assert!(!ob.is_balanced(0.5));
+}
+
+#[test]
+fn is_balanced_is_false_for_empty_book() {
+    let ob = OrderBook::new();
+
+    assert_eq!(ob.imbalance(), None);
+    assert!(!ob.is_balanced(1.0));
+}
+
+#[test]
+fn trade_vwap_evicts_trades_outside_window() {
+    use std::time::Duration;
+    use crate::config::FeedConfig;
+
+    let config = FeedConfig { trade_vwap_window: Duration::from_secs(60), ..FeedConfig::default() };
+    let mut ob = OrderBook::with_config(&config);
+    assert_eq!(ob.trade_vwap(0), None);
+
+    ob.record_trade(1000.0, 10, 0);
+    ob.record_trade(2000.0, 1, 30_000);
+    ob.record_trade(2010.0, 1, 70_000);
+
+    // The trade at 0 is older than 60s at 70s.
+    assert_eq!(ob.trade_vwap(70_000), Some(2005.0));
+    // At 100s only the last trade remains.
+    assert_eq!(ob.trade_vwap(100_000), Some(2010.0));
+    assert_eq!(ob.trade_vwap(200_000), None);
+}
+
+#[test]
+fn side_snapshot_matches_full_snapshot() {
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    ob.update(vec![(1999.0, 5), (2000.0, 15), (1998.0, 1)], vec![(2001.0, 2), (2003.0, 4)]);
+
+    let snapshot = ob.snapshot();
+    assert_eq!(ob.side_snapshot(Side::Bid), snapshot.bids);
+    assert_eq!(ob.side_snapshot(Side::Ask), vec![(2001.0, 2), (2003.0, 4)]);
+    assert_eq!(ob.side_snapshot(Side::Ask), snapshot.asks);
+}
+
+#[test]
+fn side_snapshot_of_empty_side_is_empty() {
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    ob.update(vec![(2000.0, 1)], vec![]);
+
+    assert!(ob.side_snapshot(Side::Ask).is_empty());
+    assert!(OrderBook::new().side_snapshot(Side::Bid).is_empty());
+}
+
+#[test]
+fn effective_spread_walks_levels() {
+    let mut ob = OrderBook::new();
+    ob.update(vec![(2000.0, 2), (1998.0, 8)], vec![(2001.0, 2), (2005.0, 8)]);
+
+    assert_eq!(ob.effective_spread(1), ob.spread());
+    // Buying 4 pays (2 * 2001 + 2 * 2005) / 4, selling 4 gets (2 * 2000 + 2 * 1998) / 4.
+    assert_eq!(ob.effective_spread(4), Some(2003.0 - 1999.0));
+    assert_eq!(ob.effective_spread(11), None);
+    assert_eq!(ob.effective_spread(0), None);
+}
+
+#[test]
+fn liquidity_state_classifies_sides() {
+    use crate::order_book::LiquidityState;
+
+    let mut ob = OrderBook::new();
+    assert_eq!(ob.liquidity_state(), LiquidityState::Empty);
+
+    ob.update(vec![(2000.0, 0)], vec![(2001.0, 0)]);
+    assert_eq!(ob.liquidity_state(), LiquidityState::Empty);
+
+    ob.update(vec![], vec![(2001.0, 3)]);
+    assert_eq!(ob.liquidity_state(), LiquidityState::OneSided);
+
+    ob.update(vec![(2000.0, 5)], vec![(2001.0, 3)]);
+    assert_eq!(ob.liquidity_state(), LiquidityState::Normal);
+}
+
+#[test]
+fn ndjson_output_lines_parse_independently() {
+    let mut out = String::new();
+    let mut ob = OrderBook::new();
+    for i in 0..3 {
+        ob.update(vec![(2000.0 + i as f64, 1 + i)], vec![(2010.0, 5)]);
+        out.push_str(&ob.render(OutputFormat::Json));
+    }
+
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 3);
+    for (i, line) in lines.iter().enumerate() {
+        let value: Value = serde_json::from_str(line).unwrap();
+        assert_eq!(value["bids"][0][0], 2000.0 + i as f64);
+    }
+}
+
+#[test]
+fn nearest_level_picks_closest_price() {
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    ob.update(vec![(1990.0, 1), (1995.0, 2), (2000.0, 3)], vec![(2001.0, 4), (2010.0, 5)]);
+
+    // Between two levels, the nearer one wins.
+    assert_eq!(ob.nearest_level(Side::Bid, 1996.0).map(|item| item.price), Some(1995.0));
+    assert_eq!(ob.nearest_level(Side::Bid, 1998.0).map(|item| item.price), Some(2000.0));
+    assert_eq!(ob.nearest_level(Side::Ask, 2004.0).map(|item| item.price), Some(2001.0));
+    // Exactly on a level.
+    assert_eq!(ob.nearest_level(Side::Ask, 2010.0).map(|item| item.size), Some(5));
+    // Outside the range of the side.
+    assert_eq!(ob.nearest_level(Side::Bid, 1500.0).map(|item| item.price), Some(1990.0));
+    assert_eq!(ob.nearest_level(Side::Ask, 3000.0).map(|item| item.price), Some(2010.0));
+
+    assert!(OrderBook::new().nearest_level(Side::Ask, 2000.0).is_none());
+}
+
+#[test]
+fn pressure_is_restricted_to_top_levels() {
+    let mut ob = OrderBook::new();
+    ob.update(
+        vec![(2000.0, 6), (1999.0, 4), (1998.0, 10), (1990.0, 500)],
+        vec![(2001.0, 2), (2002.0, 8), (2003.0, 5), (2010.0, 1)],
+    );
+
+    // Top of book: (6 - 2) / (6 + 2)
+    assert_eq!(ob.pressure(1), Some(0.5));
+    // Top three: (20 - 15) / (20 + 15)
+    assert_eq!(ob.pressure(3), Some(5.0 / 35.0));
+    // The far bid wall dominates the whole-book imbalance but not the near-touch pressure.
+    assert!(ob.imbalance().unwrap() > 0.9);
+    assert_eq!(ob.pressure(0), None);
+    assert_eq!(OrderBook::new().pressure(5), None);
+}
+
+#[test]
+fn levels_in_range_includes_band_edges() {
+    let mut ob = OrderBook::new();
+    ob.update(
+        vec![(1990.0, 1), (1995.0, 2), (2000.0, 3)],
+        vec![(2001.0, 4), (2005.0, 5), (2010.0, 6)],
+    );
+    let prices = |levels: Vec<&crate::item::Item>| levels.iter().map(|item| item.price).collect::<Vec<_>>();
+
+    let (bids, asks) = ob.levels_in_range(1995.0, 2005.0);
+    assert_eq!(prices(bids), vec![1995.0, 2000.0]);
+    assert_eq!(prices(asks), vec![2001.0, 2005.0]);
+
+    let (bids, asks) = ob.levels_in_range(1995.5, 2004.99);
+    assert_eq!(prices(bids), vec![2000.0]);
+    assert_eq!(prices(asks), vec![2001.0]);
+
+    let (bids, asks) = ob.levels_in_range(2100.0, 2200.0);
+    assert!(bids.is_empty() && asks.is_empty());
+
+    // An inverted band is empty.
+    let (bids, asks) = ob.levels_in_range(2005.0, 1995.0);
+    assert!(bids.is_empty() && asks.is_empty());
+}
+
+#[test]
+fn books_compare_by_symbol_and_levels() {
+    use crate::config::{FeedConfig, Market};
+
+    let mut ob = OrderBook::new();
+    let mut expected = OrderBook::new();
+    ob.update(vec![(2000.0, 15), (1999.0, 5)], vec![(2001.0, 3)]);
+    expected.update(vec![(1999.0, 5), (2000.0, 15)], vec![(2001.0, 3)]);
+    assert_eq!(ob, expected);
+
+    expected.update(vec![(2000.0, 15), (1999.0, 6)], vec![(2001.0, 3)]);
+    assert_ne!(ob, expected);
+
+    let mut other_symbol = OrderBook::with_config(&FeedConfig::new("XBTUSDTM".parse().unwrap(), Market::Futures));
+    other_symbol.update(vec![(2000.0, 15), (1999.0, 5)], vec![(2001.0, 3)]);
+    assert_ne!(ob, other_symbol);
+}
+
+#[test]
+fn apply_snapshot_restores_levels_within_depth() {
+    use crate::snapshot::OrderBookSnapshot;
+
+    let snapshot = OrderBookSnapshot {
+        symbol: "ETHUSDTM".to_string(),
+        bids: (0..8).map(|i| (2000.0 - i as f64, 1 + i)).collect(),
+        asks: vec![(2001.0, 3), (2002.0, 4)],
+        timestamp: Some(1739942942550),
+        sequence: Some(42),
+    };
+
+    let mut ob = OrderBook::new();
+    ob.update(vec![(1500.0, 1)], vec![(2500.0, 1)]);
+    ob.apply_snapshot(&snapshot);
+
+    // The default depth of 5 keeps the best five bids.
+    let restored = ob.snapshot();
+    assert_eq!(restored.bids, snapshot.bids[..5].to_vec());
+    assert_eq!(restored.asks, snapshot.asks);
+    assert_eq!(ob.timestamp(), Some(1739942942550));
+    assert_eq!(ob.last_sequence(), Some(42));
+}
+
+#[test]
+fn replayed_sequences_are_no_ops() {
+    use crate::order_book::{SequenceCheck, Side};
+
+    let mut ob = OrderBook::new();
+    assert!(ob.apply_delta_sequenced(Side::Bid, 2000.0, 10, 5));
+    assert!(ob.apply_delta_sequenced(Side::Bid, 2000.0, 0, 6));
+    // A late copy of sequence 5 must not resurrect the removed level.
+    assert!(!ob.apply_delta_sequenced(Side::Bid, 2000.0, 10, 5));
+    assert!(ob.snapshot().bids.is_empty());
+
+    assert!(ob.update_sequenced(vec![(1999.0, 3)], vec![(2001.0, 4)], 7));
+    assert!(!ob.update_sequenced(vec![(1500.0, 1)], vec![], 7));
+    assert_eq!(ob.snapshot().bids, vec![(1999.0, 3)]);
+
+    // The same sequenced message applied twice through `apply_json`.
+    let msg = serde_json::json!({
+        "data": {"bids": [["1998.0", 2]], "asks": [["2002.0", 2]], "sequence": 8}
+    });
+    assert_eq!(ob.apply_json(&msg).accepted, 2);
+    ob.apply_delta(Side::Ask, 2003.0, 9);
+    let before = ob.snapshot();
+    let replay = ob.apply_json(&msg);
+    assert_eq!(replay.sequence, Some(SequenceCheck::Stale));
+    assert_eq!(replay.accepted, 0);
+    assert_eq!(ob.snapshot(), before);
+}
+
+#[test]
+fn depth_within_bps_sums_near_touch_levels() {
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    ob.update(
+        vec![(2000.0, 10), (1999.0, 5), (1998.0, 7), (1900.0, 100)],
+        vec![(2001.0, 4), (2002.0, 6), (2100.0, 100)],
+    );
+
+    // 1 bps of 2000 is 0.2, which only covers the best level.
+    assert_eq!(ob.depth_within_bps(Side::Bid, 1.0), 10);
+    assert_eq!(ob.depth_within_bps(Side::Ask, 1.0), 4);
+    assert_eq!(ob.depth_within_bps(Side::Bid, 0.0), 10);
+
+    // 10 bps of 2000 is 2.0, reaching 1998 but not 1900.
+    assert_eq!(ob.depth_within_bps(Side::Bid, 10.0), 22);
+    assert_eq!(ob.depth_within_bps(Side::Ask, 10.0), 10);
+    assert_eq!(ob.depth_within_bps(Side::Ask, 1_000.0), 110);
+
+    assert_eq!(OrderBook::new().depth_within_bps(Side::Bid, 10.0), 0);
+}
+
+#[test]
+fn apply_json_reads_configured_depth_keys() {
+    use crate::config::FeedConfig;
+    use crate::parse::DepthKeys;
+
+    let config = FeedConfig { depth_keys: DepthKeys::ABBREVIATED, ..FeedConfig::default() };
+    let mut ob = OrderBook::with_config(&config);
+    let msg = serde_json::json!({"data": {"b": [["2000.0", 3]], "a": [["2001.0", 4]]}});
+
+    assert_eq!(ob.apply_json(&msg).accepted, 2);
+    assert_eq!(ob.snapshot().bids, vec![(2000.0, 3)]);
+    assert_eq!(ob.snapshot().asks, vec![(2001.0, 4)]);
+}
+
+#[test]
+fn slope_of_linear_book_is_constant() {
+    use crate::config::FeedConfig;
+    use crate::order_book::Side;
+
+    // Every 5 contracts the price moves by 0.5, i.e. 0.1 per contract.
+    let mut ob = OrderBook::with_config(&FeedConfig { depth: 10, ..FeedConfig::default() });
+    let asks = (0..6).map(|i| (2000.0 + 0.5 * i as f64, 5)).collect();
+    let bids = (0..6).map(|i| (1999.0 - 0.5 * i as f64, 5)).collect();
+    ob.update(bids, asks);
+
+    let ask_slope = ob.slope(Side::Ask, 30).unwrap();
+    let bid_slope = ob.slope(Side::Bid, 30).unwrap();
+    assert!((ask_slope - 0.1).abs() < 1e-9, "ask slope {}", ask_slope);
+    assert!((bid_slope + 0.1).abs() < 1e-9, "bid slope {}", bid_slope);
+
+    // Limiting the size limits the fit to the first levels, which lie on the same line.
+    assert!((ob.slope(Side::Ask, 12).unwrap() - 0.1).abs() < 1e-9);
+    assert_eq!(ob.slope(Side::Ask, 9), None);
+    assert_eq!(OrderBook::new().slope(Side::Bid, 100), None);
+}
+
+#[test]
+fn levels_past_their_ttl_are_pruned() {
+    use std::time::Duration;
+    use chrono::Utc;
+    use crate::config::FeedConfig;
+    use crate::order_book::Side;
+
+    let config = FeedConfig { level_ttl: Some(Duration::from_secs(60)), ..FeedConfig::default() };
+    let mut ob = OrderBook::with_config(&config);
+    ob.apply_delta(Side::Bid, 2000.0, 10);
+    ob.apply_delta(Side::Ask, 2001.0, 5);
+    let set_at = Utc::now().timestamp_millis();
+
+    // Within the TTL nothing is pruned.
+    assert_eq!(ob.prune_stale(set_at + 30_000), 0);
+    assert_eq!(ob.snapshot().bids, vec![(2000.0, 10)]);
+
+    // Two minutes later both levels have aged past the TTL.
+    assert_eq!(ob.prune_stale(set_at + 120_000), 2);
+    assert!(ob.snapshot().bids.is_empty());
+    assert!(ob.snapshot().asks.is_empty());
+
+    // Without a TTL levels are never pruned.
+    let mut ob = OrderBook::new();
+    ob.apply_delta(Side::Bid, 2000.0, 10);
+    assert_eq!(ob.prune_stale(i64::MAX), 0);
+}
+
+#[test]
+fn mid_return_between_updates() {
+    let mut ob = OrderBook::new();
+    assert_eq!(ob.mid_return(), None);
+
+    ob.update(vec![(99.0, 1)], vec![(101.0, 1)]);
+    assert_eq!(ob.mid_return(), None, "a single mid has no return");
+
+    ob.update(vec![(101.0, 1)], vec![(103.0, 1)]);
+    assert!((ob.mid_return().unwrap() - 0.02).abs() < 1e-12);
+
+    ob.update(vec![(100.0, 1)], vec![(104.0, 1)]);
+    assert_eq!(ob.mid_return(), Some(0.0));
+
+    // A one-sided update has no mid, so neither it nor the next update has a return.
+    ob.update(vec![(100.0, 1)], vec![]);
+    assert_eq!(ob.mid_return(), None);
+    ob.update(vec![(100.0, 1)], vec![(104.0, 1)]);
+    assert_eq!(ob.mid_return(), None);
+}
+
+#[test]
+fn entropy_of_uniform_and_single_level_sides() {
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    ob.update(
+        vec![(1996.0, 7), (1997.0, 7), (1998.0, 7), (1999.0, 7)],
+        vec![(2001.0, 40)],
+    );
+
+    let uniform = ob.entropy(Side::Bid).unwrap();
+    assert!((uniform - 4f64.ln()).abs() < 1e-12, "uniform entropy {}", uniform);
+    assert_eq!(ob.entropy(Side::Ask), Some(0.0));
+
+    // Concentrating the bids lowers their entropy below the uniform maximum.
+    ob.update(vec![(1996.0, 1), (1997.0, 1), (1998.0, 1), (1999.0, 25)], vec![]);
+    assert!(ob.entropy(Side::Bid).unwrap() < uniform);
+    assert_eq!(ob.entropy(Side::Ask), None);
+}
+
+#[test]
+fn level_memory_grows_with_levels_and_shrinks_after_churn() {
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    for i in 0..10 {
+        ob.apply_delta(Side::Bid, 2000.0 - i as f64, 1);
+    }
+    let small = ob.level_memory();
+    for i in 10..500 {
+        ob.apply_delta(Side::Bid, 2000.0 - i as f64, 1);
+    }
+    let large = ob.level_memory();
+    assert!(large > small, "{} should exceed {}", large, small);
+
+    // Removing levels keeps their capacity until the book is compacted.
+    for i in 10..500 {
+        ob.apply_delta(Side::Bid, 2000.0 - i as f64, 0);
+    }
+    assert_eq!(ob.level_memory(), large);
+    ob.shrink_to_fit();
+    assert!(ob.level_memory() < large);
+    assert_eq!(ob.bids_slice().len(), 10);
+}
+
+#[test]
+fn best_5_fills_from_the_best_price() {
+    use crate::item::Item;
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    ob.update(
+        vec![(1996.0, 1), (1997.0, 2), (1998.0, 3), (1999.0, 4), (2000.0, 5)],
+        vec![(2001.0, 6), (2002.0, 7)],
+    );
+
+    let bids = ob.best_5(Side::Bid);
+    let prices: Vec<f64> = bids.iter().map(|level| level.as_ref().unwrap().price).collect();
+    assert_eq!(prices, vec![2000.0, 1999.0, 1998.0, 1997.0, 1996.0]);
+
+    assert_eq!(
+        ob.best_5(Side::Ask),
+        [Some(Item { price: 2001.0, size: 6 }), Some(Item { price: 2002.0, size: 7 }), None, None, None]
+    );
+    assert_eq!(OrderBook::new().best_5(Side::Bid), [None, None, None, None, None]);
+}
+
+#[test]
+fn avg_order_size_per_side() {
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    ob.update(vec![(2000.0, 10), (1999.0, 20), (1998.0, 60)], vec![(2001.0, 7)]);
+
+    assert_eq!(ob.avg_order_size(Side::Bid), Some(30.0));
+    assert_eq!(ob.avg_order_size(Side::Ask), Some(7.0));
+
+    ob.update(vec![(2000.0, 10)], vec![]);
+    assert_eq!(ob.avg_order_size(Side::Ask), None);
+    assert_eq!(OrderBook::new().avg_order_size(Side::Bid), None);
+}
+
+#[test]
+fn merge_sums_overlapping_levels_and_keeps_distinct_ones() {
+    let mut first = OrderBook::new();
+    first.update(
+        vec![(2000.0, 10), (1999.0, 5), (1997.0, 1)],
+        vec![(2001.0, 4), (2003.0, 2)],
+    );
+    let mut second = OrderBook::new();
+    second.update(
+        vec![(2000.0, 3), (1998.0, 8), (1997.0, 2)],
+        vec![(2001.0, 6), (2002.0, 9)],
+    );
+
+    let merged = first.merge(&second);
+
+    assert_eq!(
+        merged.snapshot().bids,
+        vec![(2000.0, 13), (1999.0, 5), (1998.0, 8), (1997.0, 3)]
+    );
+    assert_eq!(merged.snapshot().asks, vec![(2001.0, 10), (2002.0, 9), (2003.0, 2)]);
+    assert_eq!(merged.symbol(), first.symbol());
+    // The inputs are untouched.
+    assert_eq!(first.snapshot().bids.len(), 3);
+    assert_eq!(merged, second.merge(&first));
+}
+
+#[test]
+fn level_count_histogram_buckets_updates() {
+    use std::collections::BTreeMap;
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    let levels = |n: usize, start: f64, step: f64| -> Vec<(f64, i64)> {
+        (0..n).map(|i| (start + step * i as f64, 1)).collect()
+    };
+
+    for _ in 0..3 {
+        ob.update(levels(5, 2000.0, -1.0), levels(5, 2001.0, 1.0));
+    }
+    ob.update(levels(3, 2000.0, -1.0), levels(5, 2001.0, 1.0));
+    ob.update(levels(0, 2000.0, -1.0), levels(4, 2001.0, 1.0));
+
+    assert_eq!(ob.price_levels_count_histogram(Side::Bid), &BTreeMap::from([(0, 1), (3, 1), (5, 3)]));
+    assert_eq!(ob.price_levels_count_histogram(Side::Ask), &BTreeMap::from([(4, 1), (5, 4)]));
+}
+
+#[test]
+fn kucoin_value_round_trips_through_the_parser() {
+    let msg = serde_json::json!({
+        "data": {
+            "bids": [["2678.35", "12"], ["2677.99", "197"], ["2677.5", "3"]],
+            "asks": [["2678.36", "4356"], ["2678.37", "2931"]],
+            "timestamp": 1739942942550i64
+        }
+    });
+    let mut ob = OrderBook::new();
+    ob.apply_json(&msg);
+
+    let value = ob.to_kucoin_value();
+    assert_eq!(value["data"]["bids"][0], serde_json::json!(["2678.35", "12"]));
+    assert_eq!(value["data"]["asks"][1], serde_json::json!(["2678.37", "2931"]));
+
+    let mut copy = OrderBook::new();
+    assert_eq!(copy.apply_json(&value).accepted, 5);
+    assert_eq!(copy.snapshot(), ob.snapshot());
+    assert_eq!(copy.timestamp(), Some(1739942942550));
+}
+
+#[test]
+fn after_sweep_consumes_levels_from_the_best_price() {
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    ob.update(
+        vec![(2000.0, 10), (1999.0, 20), (1998.0, 30)],
+        vec![(2001.0, 5), (2002.0, 5), (2003.0, 50)],
+    );
+
+    let swept = ob.after_sweep(Side::Bid, 25);
+    assert_eq!(swept.snapshot().bids, vec![(1999.0, 5), (1998.0, 30)]);
+    assert_eq!(swept.snapshot().asks, ob.snapshot().asks, "the other side is untouched");
+
+    let swept = ob.after_sweep(Side::Ask, 12);
+    assert_eq!(swept.snapshot().asks, vec![(2003.0, 48)]);
+
+    // Sweeping exactly the best level removes it, sweeping more than the side empties it.
+    assert_eq!(ob.after_sweep(Side::Ask, 5).snapshot().asks, vec![(2002.0, 5), (2003.0, 50)]);
+    assert!(ob.after_sweep(Side::Bid, 1_000).snapshot().bids.is_empty());
+
+    // The original book is unchanged.
+    assert_eq!(ob.snapshot().bids.len(), 3);
+    assert_eq!(ob.after_sweep(Side::Bid, 0), ob);
+}
+
+#[test]
+fn since_last_update_is_measured_from_the_last_update() {
+    use std::time::{Duration, Instant};
+
+    let mut ob = OrderBook::new();
+    assert_eq!(ob.since_last_update(Instant::now()), None);
+
+    let before = Instant::now();
+    ob.update(vec![(2000.0, 15)], vec![(2001.0, 5)]);
+    let after = Instant::now();
+
+    let later = after + Duration::from_secs(3);
+    let elapsed = ob.since_last_update(later).unwrap();
+    assert!(elapsed >= Duration::from_secs(3));
+    assert!(elapsed <= later - before);
+
+    // An instant before the update saturates to zero.
+    assert_eq!(ob.since_last_update(before), Some(Duration::ZERO));
+}
+
+#[test]
+fn apply_batch_reports_each_message_in_order() {
+    use crate::order_book::SequenceCheck;
+
+    let msg = |bid: f64, start: u64, end: u64| {
+        serde_json::json!({
+            "data": {"bids": [[bid, 1]], "asks": [[2010.0, 2]], "sequenceStart": start, "sequenceEnd": end}
+        })
+    };
+    let mut ob = OrderBook::new();
+
+    let reports = ob.apply_batch(&[msg(2000.0, 10, 12), msg(2001.0, 13, 15), msg(1999.0, 11, 14)]);
+
+    assert_eq!(reports.len(), 3);
+    assert_eq!(reports[0].sequence, Some(SequenceCheck::First));
+    assert_eq!(reports[1].sequence, Some(SequenceCheck::Contiguous));
+    assert_eq!(reports[2].sequence, Some(SequenceCheck::Stale));
+    assert_eq!(reports[2].accepted, 0);
+    assert_eq!(ob.snapshot().bids, vec![(2001.0, 1)], "the stale message is not applied");
+    assert_eq!(ob.last_sequence(), Some(15));
+}
+
+#[test]
+fn missing_side_names_the_empty_side_of_a_one_sided_book() {
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    assert_eq!(ob.missing_side(), None, "an empty book is not one-sided");
+
+    ob.update(vec![(2000.0, 15)], vec![]);
+    assert_eq!(ob.missing_side(), Some(Side::Ask));
+
+    ob.update(vec![], vec![(2001.0, 5)]);
+    assert_eq!(ob.missing_side(), Some(Side::Bid));
+
+    ob.update(vec![(2000.0, 15)], vec![(2001.0, 5)]);
+    assert_eq!(ob.missing_side(), None);
+}
+
+#[test]
+fn spot_changes_are_applied_as_deltas() {
+    let mut ob = OrderBook::new();
+    ob.update(vec![(18900.0, 3), (18904.0, 1)], vec![(18906.0, 5), (18910.0, 2)]);
+
+    let msg = serde_json::json!({
+        "type": "message",
+        "topic": "/market/level2:BTC-USDT",
+        "subject": "trade.l2update",
+        "data": {
+            "changes": {
+                "asks": [["18906", "0", "1590"]],
+                "bids": [["18904", "4", "1591"], ["18901", "7", "1592"]]
+            },
+            "sequenceStart": 1590,
+            "sequenceEnd": 1592,
+            "symbol": "BTC-USDT"
+        }
+    });
+    let report = ob.apply_json(&msg);
+
+    assert_eq!(report.accepted, 3);
+    assert_eq!(ob.snapshot().bids, vec![(18904.0, 4), (18901.0, 7), (18900.0, 3)]);
+    assert_eq!(ob.snapshot().asks, vec![(18910.0, 2)], "a zero size removes the level");
+    assert_eq!(ob.last_sequence(), Some(1592));
+}
+
+#[test]
+fn outlier_prices_are_dropped_by_the_sanity_filter() {
+    use crate::config::FeedConfig;
+
+    let config = FeedConfig { max_price_deviation: Some(5.0), ..FeedConfig::default() };
+    let mut ob = OrderBook::with_config(&config);
+
+    // The first update has no mid to compare against and is taken as is.
+    ob.update(vec![(1999.0, 10)], vec![(2001.0, 10)]);
+
+    ob.update(vec![(1998.0, 5), (1.0, 100)], vec![(2002.0, 5), (9000.0, 1)]);
+    assert_eq!(ob.snapshot().bids, vec![(1998.0, 5)], "the fat-finger bid is dropped");
+    assert_eq!(ob.snapshot().asks, vec![(2002.0, 5)]);
+
+    // Without the filter the same levels are kept.
+    let mut unfiltered = OrderBook::new();
+    unfiltered.update(vec![(1999.0, 10)], vec![(2001.0, 10)]);
+    unfiltered.update(vec![(1998.0, 5), (1.0, 100)], vec![(2002.0, 5), (9000.0, 1)]);
+    assert_eq!(unfiltered.snapshot().bids.len(), 2);
+}
+
+#[test]
+fn snapshot_at_sequence_looks_up_the_history() {
+    use crate::config::FeedConfig;
+
+    let config = FeedConfig { snapshot_history: 3, ..FeedConfig::default() };
+    let mut ob = OrderBook::with_config(&config);
+    for (i, sequence) in (10..14).enumerate() {
+        let msg = serde_json::json!({
+            "data": {"bids": [[2000.0 + i as f64, 1]], "asks": [[2010.0, 2]], "sequence": sequence}
+        });
+        ob.apply_json(&msg);
+    }
+
+    let snapshot = ob.snapshot_at_sequence(12).unwrap();
+    assert_eq!(snapshot.bids, vec![(2002.0, 1)]);
+    assert_eq!(snapshot.sequence, Some(12));
+    assert_eq!(ob.snapshot_at_sequence(13).unwrap().bids, vec![(2003.0, 1)]);
+
+    assert!(ob.snapshot_at_sequence(10).is_none(), "evicted from the history");
+    assert!(ob.snapshot_at_sequence(99).is_none(), "never applied");
+}
+
+#[test]
+fn negative_zero_price_shares_the_zero_level() {
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    ob.apply_delta(Side::Bid, 0.0, 5);
+    ob.apply_delta(Side::Bid, -0.0, 7);
+    assert_eq!(ob.bids_slice().len(), 1);
+    assert_eq!(ob.snapshot().bids, vec![(0.0, 7)], "the second delta resizes the same level");
+
+    ob.update(vec![(-0.0, 3), (0.0, 4)], vec![]);
+    assert_eq!(ob.bids_slice().len(), 1, "the first of the two zero levels wins");
+    assert!(ob.best_bid().unwrap().is_sign_positive());
+}
+
+#[test]
+fn center_of_mass_weights_both_sides_by_size() {
+    let mut ob = OrderBook::new();
+    assert_eq!(ob.center_of_mass(), None);
+
+    ob.update(vec![(2000.0, 10), (1999.0, 5)], vec![(2002.0, 10), (2003.0, 5)]);
+    let center = ob.center_of_mass().unwrap();
+    assert!((center - ob.mid().unwrap()).abs() < 1e-9, "a symmetric book is centered on the mid");
+
+    ob.update(vec![(2000.0, 10), (1999.0, 90)], vec![(2002.0, 10), (2003.0, 5)]);
+    let center = ob.center_of_mass().unwrap();
+    assert!(center < ob.mid().unwrap(), "deep bids pull the center below the mid");
+    assert!((center - 229_945.0 / 115.0).abs() < 1e-9);
+}
+
+#[test]
+fn resync_replays_buffered_messages_newer_than_the_snapshot() {
+    use crate::snapshot::OrderBookSnapshot;
+
+    let delta = |sequence: u64, bids: Value, asks: Value| {
+        serde_json::json!({
+            "data": {"changes": {"bids": bids, "asks": asks}, "sequenceStart": sequence, "sequenceEnd": sequence}
+        })
+    };
+    let mut ob = OrderBook::new();
+    ob.update(vec![(1990.0, 1)], vec![(2020.0, 1)]);
+    ob.apply_json(&delta(20, serde_json::json!([]), serde_json::json!([])));
+
+    // Reconnected: the snapshot was taken at 11, while 10 to 13 were buffered
+    let snapshot = OrderBookSnapshot {
+        bids: vec![(2000.0, 5), (1999.0, 3)],
+        asks: vec![(2001.0, 2)],
+        sequence: Some(11),
+        ..Default::default()
+    };
+    let buffered = [
+        delta(10, serde_json::json!([[2000.0, 1]]), serde_json::json!([])),
+        delta(11, serde_json::json!([]), serde_json::json!([[2001.0, 0]])),
+        delta(12, serde_json::json!([[1999.0, 0]]), serde_json::json!([])),
+        delta(13, serde_json::json!([]), serde_json::json!([[2002.0, 4]])),
+    ];
+
+    assert_eq!(ob.resync(&snapshot, &buffered), 2);
+    assert_eq!(ob.snapshot().bids, vec![(2000.0, 5)]);
+    assert_eq!(ob.snapshot().asks, vec![(2001.0, 2), (2002.0, 4)]);
+    assert_eq!(ob.last_sequence(), Some(13), "the snapshot's sequence replaces the pre-reconnect one");
+}
+
+#[test]
+fn tick_gaps_reports_missing_ticks_on_each_side() {
+    use crate::order_book::Side;
+
+    let mut ob = OrderBook::new();
+    ob.update(
+        vec![(2000.0, 10), (1999.5, 5), (1998.0, 5)],
+        vec![(2000.5, 1), (2001.0, 2), (2002.5, 3), (2003.0, 1)],
+    );
+
+    assert_eq!(ob.tick_gaps(Side::Bid, 0.5), vec![1999.0, 1998.5]);
+    assert_eq!(ob.tick_gaps(Side::Ask, 0.5), vec![2001.5, 2002.0]);
+
+    // A coarser tick sees fewer gaps, and an invalid tick none at all.
+    assert_eq!(ob.tick_gaps(Side::Ask, 1.0), vec![2002.0]);
+    assert!(ob.tick_gaps(Side::Bid, 0.0).is_empty());
+
+    ob.update(vec![(2000.0, 10), (1999.5, 5)], vec![]);
+    assert!(ob.tick_gaps(Side::Bid, 0.5).is_empty(), "a dense side has no gaps");
+}
+
+#[test]
+fn tick_gaps_are_bounded() {
+    use crate::order_book::{Side, MAX_TICK_GAPS};
+
+    let mut ob = OrderBook::new();
+    ob.update(vec![(2000.0, 10), (1000.0, 5)], vec![(2001.0, 1), (2002.0, 1)]);
+
+    let gaps = ob.tick_gaps(Side::Bid, 1e-12);
+    assert_eq!(gaps.len(), MAX_TICK_GAPS);
+    assert!(gaps.windows(2).all(|pair| pair[0] > pair[1]), "the best gaps are kept, best first");
+    assert!(gaps[0] < 2000.0);
+
+    assert!(ob.tick_gaps(Side::Ask, f64::NAN).is_empty());
+    assert!(ob.tick_gaps(Side::Ask, f64::INFINITY).is_empty());
+    assert!(ob.tick_gaps(Side::Ask, -1.0).is_empty());
+}
+
+#[test]
+fn one_sided_books_collect_from_levels() {
+    use crate::order_book::{Asks, Bids, LiquidityState};
+
+    let bids: Bids = [(59900.0, 2), (60000.0, 1), (59800.0, 0)].into_iter().collect();
+    assert_eq!(bids.0.len(), 3);
+    let ob = OrderBook::from(bids);
+    assert_eq!(ob.snapshot().bids, vec![(60000.0, 1), (59900.0, 2)]);
+    assert!(ob.snapshot().asks.is_empty());
+    assert_eq!(ob.liquidity_state(), LiquidityState::OneSided);
+
+    let asks: Asks = (0..3).map(|i| (60100.0 + i as f64 * 100.0, i + 1)).collect();
+    let ob = OrderBook::from(asks);
+    assert_eq!(ob.snapshot().asks, vec![(60100.0, 1), (60200.0, 2), (60300.0, 3)]);
+    assert_eq!(ob.best_bid(), None);
+}
+
+#[test]
+fn extreme_imbalance_keeps_the_most_one_sided_update() {
+    let mut ob = OrderBook::new();
+    let msg = |ts: i64, bid_size: i64, ask_size: i64| {
+        serde_json::json!({
+            "data": {"bids": [["100.0", bid_size]], "asks": [["101.0", ask_size]], "timestamp": ts}
+        })
+    };
+    assert_eq!(ob.extreme_imbalance(), None);
+
+    ob.apply_json(&msg(1_000, 30, 10));
+    assert_eq!(ob.extreme_imbalance(), Some((1_000, 0.5)));
+
+    ob.apply_json(&msg(2_000, 5, 45));
+    ob.apply_json(&msg(3_000, 40, 10));
+    ob.apply_json(&msg(4_000, 10, 10));
+    assert_eq!(ob.extreme_imbalance(), Some((2_000, -0.8)), "a larger magnitude wins regardless of sign");
+
+    ob.apply_json(&msg(5_000, 95, 5));
+    assert_eq!(ob.extreme_imbalance(), Some((5_000, 0.9)));
+}
@@ -1,10 +1,162 @@
-use std::collections::BTreeSet;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant};
+#[cfg(feature = "arrow")]
+use std::sync::Arc;
+#[cfg(feature = "arrow")]
+use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray};
+#[cfg(feature = "arrow")]
+use arrow_schema::{DataType, Field, Schema};
+use chrono::Utc;
+use serde_json::Value;
+#[cfg(feature = "pretty")]
 use prettytable::{Table, Row, Cell};
 use crate::item::Item;
+use crate::config::{FeedConfig, OutputFormat};
+#[cfg(not(feature = "pretty"))]
+use crate::format::plain_table;
+use crate::format::{align_decimals, compact_line, header_line, ticker_line};
+#[cfg(feature = "pretty")]
+use crate::format::level_style;
+use crate::parse::{coerce_f64, coerce_i64, parse_changes_with, parse_depth_with, parse_timestamp, DepthKeys, LevelLayout, SequenceRange};
+use crate::snapshot::OrderBookSnapshot;
+use crate::symbol::Symbol;
+
+/// One side of the order book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+/// How a message's sequence range relates to the last one applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceCheck {
+    /// No sequenced message had been applied yet.
+    First,
+    /// The message starts right after the previous `sequenceEnd`.
+    Contiguous,
+    /// Messages between the previous `sequenceEnd` and this one's start were missed.
+    Gap { expected: u64, got: u64 },
+    /// The message ends at or before the previous `sequenceEnd`.
+    Stale,
+}
+
+/// Whether the book holds enough levels for prices such as the spread and mid to be meaningful.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiquidityState {
+    /// Both sides hold at least one level.
+    Normal,
+    /// Only one side holds levels.
+    OneSided,
+    /// Neither side holds any level.
+    Empty,
+}
+
+/// Describes how much of a message was applied to the book.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UpdateReport {
+    /// Levels that were parsed and applied.
+    pub accepted: usize,
+    /// Levels that were dropped because they were malformed or had a negative size.
+    pub rejected: usize,
+    /// The continuity check of the message's sequence range, if it carried one.
+    pub sequence: Option<SequenceCheck>,
+}
+
+/// Bid levels as `(price, size)`, collected from an iterator to build a one-sided book.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::order_book::{Bids, OrderBook};
+/// let bids: Bids = [(60000.0, 1), (59900.0, 2)].into_iter().collect();
+/// let order_book = OrderBook::from(bids);
+/// assert_eq!(order_book.best_bid(), Some(60000.0));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bids(pub Vec<(f64, i64)>);
+
+/// Ask levels as `(price, size)`, collected from an iterator to build a one-sided book.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::order_book::{Asks, OrderBook};
+/// let asks: Asks = [(60100.0, 1), (60200.0, 2)].into_iter().collect();
+/// let order_book = OrderBook::from(asks);
+/// assert_eq!(order_book.best_ask(), Some(60100.0));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Asks(pub Vec<(f64, i64)>);
+
+impl FromIterator<(f64, i64)> for Bids {
+    fn from_iter<I: IntoIterator<Item = (f64, i64)>>(iter: I) -> Self {
+        Bids(iter.into_iter().collect())
+    }
+}
+
+impl FromIterator<(f64, i64)> for Asks {
+    fn from_iter<I: IntoIterator<Item = (f64, i64)>>(iter: I) -> Self {
+        Asks(iter.into_iter().collect())
+    }
+}
+
+/// The most gaps [`OrderBook::tick_gaps`] reports, so a tiny tick size over a wide side cannot
+/// allocate without bound.
+pub const MAX_TICK_GAPS: usize = 10_000;
+
+/// A function applied to every price before it is inserted into the book.
+pub type PriceTransform = Box<dyn Fn(f64) -> f64 + Send + Sync>;
 
 pub struct OrderBook {
-    bids: BTreeSet<Item>,
-    asks: BTreeSet<Item>,
+    symbol: Symbol,
+    depth: usize,
+    depth_keys: DepthKeys,
+    level_layout: LevelLayout,
+    max_levels: usize,
+    multiplier: f64,
+    timestamp: Option<i64>,
+    /// When the last update was applied, by the local monotonic clock.
+    updated_at: Option<Instant>,
+    /// The `sequenceEnd` of the last sequenced message applied.
+    last_sequence: Option<u64>,
+    /// `(timestamp, best bid size, best ask size)` after the last two timestamped updates, oldest first.
+    top_sizes: [Option<(i64, i64, i64)>; 2],
+    /// The mid after the last two updates, oldest first.
+    mids: [Option<f64>; 2],
+    /// How many updates left the bid side with each number of levels.
+    bid_level_counts: BTreeMap<usize, u64>,
+    /// How many updates left the ask side with each number of levels.
+    ask_level_counts: BTreeMap<usize, u64>,
+    /// `(timestamp, imbalance)` of the imbalance furthest from zero seen this session.
+    extreme_imbalance: Option<(i64, f64)>,
+    price_transform: Option<PriceTransform>,
+    last_trade: Option<f64>,
+    /// `(price, size, timestamp)` of recent trades, oldest first.
+    trades: VecDeque<(f64, i64, i64)>,
+    trade_window_ms: i64,
+    /// Levels larger than this are highlighted in the printed table.
+    #[cfg_attr(not(feature = "pretty"), allow(dead_code))]
+    wall_threshold: Option<i64>,
+    /// `(timestamp, spread)` samples, oldest first.
+    spread_history: VecDeque<(i64, f64)>,
+    spread_history_capacity: usize,
+    /// Snapshots taken after each sequenced update, oldest first.
+    snapshot_history: VecDeque<OrderBookSnapshot>,
+    snapshot_history_capacity: usize,
+    /// How long a level may go without being set before it is pruned as stale.
+    level_ttl_ms: Option<i64>,
+    /// How far from the mid, in percent, a snapshot level may be priced before it is dropped.
+    max_price_deviation: Option<f64>,
+    /// When each level was last set, in milliseconds, keyed by side and price bits. Only tracked
+    /// with a level TTL.
+    touched: HashMap<(Side, u64), i64>,
+    /// Bid levels sorted by ascending price.
+    bids: Vec<Item>,
+    /// Ask levels sorted by ascending price.
+    asks: Vec<Item>,
 }
 
 impl OrderBook {
@@ -14,18 +166,187 @@ impl OrderBook {
     /// # Examples
     ///
     /// ```
+    /// # use warmup_project::order_book::OrderBook;
     /// let order_book = OrderBook::new();
     /// ```
     pub fn new() -> Self {
+        Self::with_config(&FeedConfig::default())
+    }
+
+    /// Creates a new, empty `OrderBook` for the symbol and depth of `config`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::config::{FeedConfig, Market};
+    /// # use warmup_project::order_book::OrderBook;
+    /// # use warmup_project::symbol::Symbol;
+    /// let order_book = OrderBook::with_config(&FeedConfig::new(Symbol::new("XBTUSDTM").unwrap(), Market::Futures));
+    /// assert_eq!(order_book.symbol(), "XBTUSDTM");
+    /// ```
+    pub fn with_config(config: &FeedConfig) -> Self {
         Self {
-            bids: BTreeSet::new(),
-            asks: BTreeSet::new(),
+            symbol: config.symbol.clone(),
+            depth: config.depth,
+            depth_keys: config.depth_keys,
+            level_layout: config.level_layout,
+            max_levels: config.max_levels,
+            multiplier: config.multiplier_for(config.symbol.as_str()),
+            timestamp: None,
+            updated_at: None,
+            last_sequence: None,
+            top_sizes: [None, None],
+            mids: [None, None],
+            bid_level_counts: BTreeMap::new(),
+            ask_level_counts: BTreeMap::new(),
+            extreme_imbalance: None,
+            price_transform: None,
+            last_trade: None,
+            trades: VecDeque::new(),
+            trade_window_ms: config.trade_vwap_window.as_millis() as i64,
+            wall_threshold: config.print.wall_threshold,
+            spread_history: VecDeque::with_capacity(config.spread_history),
+            spread_history_capacity: config.spread_history,
+            snapshot_history: VecDeque::with_capacity(config.snapshot_history),
+            snapshot_history_capacity: config.snapshot_history,
+            level_ttl_ms: config.level_ttl.map(|ttl| ttl.as_millis() as i64),
+            max_price_deviation: config.max_price_deviation,
+            touched: HashMap::new(),
+            bids: Vec::new(),
+            asks: Vec::new(),
+        }
+    }
+
+    /// Builds a depth-20 book from a KuCoin `level2/depth20` REST response.
+    ///
+    /// Unlike the depth-5 WebSocket path, all 20 levels per side are kept. The symbol is taken
+    /// from `data.symbol` when present.
+    ///
+    /// # Example JSON Input
+    ///
+    /// ```json
+    /// {
+    ///   "code": "200000",
+    ///   "data": {
+    ///     "symbol": "ETHUSDTM",
+    ///     "sequence": 1708804089297,
+    ///     "bids": [[2678.35, 12], [2677.99, 197]],
+    ///     "asks": [[2678.36, 4356], [2678.37, 2931]],
+    ///     "ts": 1739942942550000000
+    ///   }
+    /// }
+    /// ```
+    pub fn from_depth20(value: &Value) -> OrderBook {
+        let mut config = FeedConfig { depth: 20, ..FeedConfig::default() };
+        if let Some(symbol) = value["data"]["symbol"].as_str().and_then(|s| Symbol::new(s).ok()) {
+            config.symbol = symbol;
+        }
+
+        let mut ob = OrderBook::with_config(&config);
+        ob.apply_json(value);
+        ob
+    }
+
+    /// Registers a function applied to each price during [`OrderBook::update`], e.g. an FX rate.
+    ///
+    /// Levels are sorted by their transformed price.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// let usd_to_eur = 0.9;
+    /// order_book.set_price_transform(Box::new(move |price| price * usd_to_eur));
+    /// order_book.update(vec![(2000.0, 15)], vec![]);
+    /// assert_eq!(order_book.snapshot().bids, vec![(1800.0, 15)]);
+    /// ```
+    pub fn set_price_transform(&mut self, transform: PriceTransform) {
+        self.price_transform = Some(transform);
+    }
+
+    /// Removes the price transform, if any.
+    pub fn clear_price_transform(&mut self) {
+        self.price_transform = None;
+    }
+
+    /// The symbol this book tracks.
+    pub fn symbol(&self) -> &Symbol {
+        &self.symbol
+    }
+
+    /// The maximum number of levels kept on each side.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Converts a size in contracts into base-currency units using the symbol's multiplier.
+    pub fn to_base_currency(&self, contracts: i64) -> f64 {
+        contracts as f64 * self.multiplier
+    }
+
+    /// The event timestamp (in milliseconds) of the last message applied with [`OrderBook::apply_json`].
+    pub fn timestamp(&self) -> Option<i64> {
+        self.timestamp
+    }
+
+    /// How long before `now` the last update was applied, or `None` before any update.
+    ///
+    /// Unlike the event [`OrderBook::timestamp`], this is measured on the local monotonic clock,
+    /// so it is unaffected by the exchange's clock and also covers updates without a timestamp.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::time::{Duration, Instant};
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// assert_eq!(order_book.since_last_update(Instant::now()), None);
+    /// order_book.update(vec![(2000.0, 15)], vec![]);
+    /// assert!(order_book.since_last_update(Instant::now()).unwrap() < Duration::from_secs(1));
+    /// ```
+    pub fn since_last_update(&self, now: Instant) -> Option<Duration> {
+        self.updated_at.map(|updated_at| now.saturating_duration_since(updated_at))
+    }
+
+    /// The `sequenceEnd` (or `sequence`) of the last sequenced message applied.
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.last_sequence
+    }
+
+    /// Sets the sequence of the last message applied, or forgets it so the next one is `First`,
+    /// e.g. for feeds that restart their numbering with each snapshot.
+    pub(crate) fn set_last_sequence(&mut self, sequence: Option<u64>) {
+        self.last_sequence = sequence;
+    }
+
+    /// Checks a message's sequence range for continuity against the previous `sequenceEnd`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, SequenceCheck};
+    /// # use warmup_project::parse::SequenceRange;
+    /// let order_book = OrderBook::new();
+    /// let range = SequenceRange { start: 10, end: 12 };
+    /// assert_eq!(order_book.check_sequence(range), SequenceCheck::First);
+    /// ```
+    pub fn check_sequence(&self, range: SequenceRange) -> SequenceCheck {
+        match self.last_sequence {
+            None => SequenceCheck::First,
+            Some(last) if range.end <= last => SequenceCheck::Stale,
+            Some(last) if range.start == last + 1 => SequenceCheck::Contiguous,
+            Some(last) => SequenceCheck::Gap { expected: last + 1, got: range.start },
         }
     }
 
     /// Updates the order book with new bid and ask data.
     ///
-    /// If the size of either the bids or asks set exceeds 5, pop to maintain the sizec
+    /// If the size of either the bids or asks set exceeds the configured depth, the worst levels are popped.
+    /// Levels with a size of zero or less carry no liquidity and are not stored, and levels with
+    /// a `NaN` or infinite price are skipped rather than polluting the book. Prices pass through
+    /// the transform registered with [`OrderBook::set_price_transform`], if any. With
+    /// `FeedConfig::max_price_deviation` set, levels priced too far from the current mid are dropped.
     ///
     /// # Arguments
     ///
@@ -35,58 +356,1461 @@ impl OrderBook {
     /// # Examples
     ///
     /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// # let mut order_book = OrderBook::new();
     /// // Update with new bids and asks.
     /// order_book.update(vec![(2000.0, 15)], vec![(2010.0, 20)]);
     /// ```
     pub fn update(&mut self, bids: Vec<(f64, i64)>, asks: Vec<(f64, i64)>) {
-        self.bids.clear();
-        self.asks.clear();
-        for item in bids {
-            self.bids.insert(Item {price: item.0, size: item.1});
+        self.replace_levels(bids, asks);
+        self.after_update(None);
+    }
+
+    /// Replaces the book with the levels of `snapshot`, e.g. one restored from a recording or
+    /// fetched with [`crate::api::fetch_snapshot`].
+    ///
+    /// As with [`OrderBook::update`], only the best `depth` levels per side are kept and the price
+    /// transform, if any, is applied. The snapshot's timestamp and sequence are adopted when present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// # use warmup_project::snapshot::OrderBookSnapshot;
+    /// let snapshot = OrderBookSnapshot { bids: vec![(2000.0, 15)], asks: vec![(2010.0, 20)], ..Default::default() };
+    /// let mut order_book = OrderBook::new();
+    /// order_book.apply_snapshot(&snapshot);
+    /// assert_eq!(order_book.snapshot().bids, snapshot.bids);
+    /// ```
+    pub fn apply_snapshot(&mut self, snapshot: &OrderBookSnapshot) {
+        self.replace_levels(snapshot.bids.clone(), snapshot.asks.clone());
+        if snapshot.sequence.is_some() {
+            self.last_sequence = snapshot.sequence;
+        }
+        self.after_update(snapshot.timestamp);
+    }
+
+    /// Replaces the book with `snapshot`, then applies the `buffered` messages received while it
+    /// was being fetched, e.g. after a reconnect.
+    ///
+    /// Buffered messages ending at or before the snapshot's sequence are already reflected in it
+    /// and are skipped, the others are applied in order with [`OrderBook::apply_json`]. Returns
+    /// the number of buffered messages applied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// # use warmup_project::snapshot::OrderBookSnapshot;
+    /// let snapshot = OrderBookSnapshot { bids: vec![(2000.0, 15)], sequence: Some(11), ..Default::default() };
+    /// let buffered = [
+    ///     serde_json::json!({"data": {"changes": {"bids": [["2000.0", 3]]}, "sequence": 11}}),
+    ///     serde_json::json!({"data": {"changes": {"bids": [["1999.0", 4]]}, "sequence": 12}}),
+    /// ];
+    /// let mut order_book = OrderBook::new();
+    /// assert_eq!(order_book.resync(&snapshot, &buffered), 1);
+    /// assert_eq!(order_book.snapshot().bids, vec![(2000.0, 15), (1999.0, 4)]);
+    /// ```
+    pub fn resync(&mut self, snapshot: &OrderBookSnapshot, buffered: &[Value]) -> usize {
+        self.apply_snapshot(snapshot);
+        self.apply_batch(buffered)
+            .iter()
+            .filter(|report| report.sequence != Some(SequenceCheck::Stale))
+            .count()
+    }
+
+    /// Combines the levels of `self` and `other` into a new consolidated book, e.g. across venues.
+    ///
+    /// Sizes at identical prices are summed and all other levels are kept, so the result may
+    /// hold up to the depth of both books per side. The result keeps this book's symbol and the
+    /// later of the two timestamps. Consolidated books can be crossed when the venues disagree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut kucoin = OrderBook::new();
+    /// kucoin.update(vec![(2000.0, 10)], vec![(2001.0, 5)]);
+    /// let mut other = OrderBook::new();
+    /// other.update(vec![(2000.0, 4)], vec![(2002.0, 3)]);
+    ///
+    /// let merged = kucoin.merge(&other);
+    /// assert_eq!(merged.snapshot().bids, vec![(2000.0, 14)]);
+    /// assert_eq!(merged.snapshot().asks, vec![(2001.0, 5), (2002.0, 3)]);
+    /// ```
+    pub fn merge(&self, other: &OrderBook) -> OrderBook {
+        let config = FeedConfig {
+            symbol: self.symbol.clone(),
+            depth: self.depth + other.depth,
+            max_levels: self.max_levels + other.max_levels,
+            ..FeedConfig::default()
+        };
+        let mut merged = OrderBook::with_config(&config);
+        merged.multiplier = self.multiplier;
+        merged.bids = merge_sides(&self.bids, &other.bids);
+        merged.asks = merge_sides(&self.asks, &other.asks);
+        merged.after_update(self.timestamp.max(other.timestamp));
+        merged
+    }
+
+    /// Replaces both sides with the given levels, keeping the best `depth` of each.
+    ///
+    /// This is the hot path of depth snapshots, so the existing allocations are refilled in place
+    /// and each side is sorted once. As with a `BTreeSet`, the first of several levels sharing a
+    /// price wins. With `FeedConfig::max_price_deviation`, levels too far from the mid before the
+    /// update are dropped; an empty or one-sided book has no mid and accepts every price.
+    pub(crate) fn replace_levels(&mut self, bids: Vec<(f64, i64)>, asks: Vec<(f64, i64)>) {
+        self.replace_levels_keeping(bids, asks, self.depth);
+    }
+
+    /// Like [`OrderBook::replace_levels`], keeping the best `keep` levels of each side instead of
+    /// `depth`, e.g. for feeds whose deltas maintain a deeper book than the configured depth.
+    pub(crate) fn replace_levels_keeping(&mut self, bids: Vec<(f64, i64)>, asks: Vec<(f64, i64)>, keep: usize) {
+        let transform = |price: f64| match &self.price_transform {
+            Some(transform) => transform(price),
+            None => price,
+        };
+
+        let mid = self.mid();
+        refill_side(&mut self.bids, bids, transform);
+        refill_side(&mut self.asks, asks, transform);
+
+        if let (Some(mid), Some(max_deviation)) = (mid, self.max_price_deviation) {
+            let sane = |item: &Item| ((item.price - mid) / mid).abs() * 100.0 <= max_deviation;
+            let before = self.bids.len() + self.asks.len();
+            self.bids.retain(sane);
+            self.asks.retain(sane);
+            let dropped = before - self.bids.len() - self.asks.len();
+            if dropped > 0 {
+                log::warn!("⚠️ Dropped {} levels more than {}% away from the mid of {}", dropped, max_deviation, mid);
+            }
+        }
+
+        if self.bids.len() > keep {
+            let excess = self.bids.len() - keep;
+            self.bids.drain(..excess);
+        }
+        self.asks.truncate(keep);
+
+        if self.level_ttl_ms.is_some() {
+            let now = Utc::now().timestamp_millis();
+            self.touched.clear();
+            for item in &self.bids {
+                self.touched.insert((Side::Bid, item.price.to_bits()), now);
+            }
+            for item in &self.asks {
+                self.touched.insert((Side::Ask, item.price.to_bits()), now);
+            }
+        }
+    }
+
+    /// Records per-update history once new levels are in place, and prunes stale levels when a
+    /// level TTL is configured.
+    ///
+    /// `event_ts` is the message's event timestamp in milliseconds, when it carried one.
+    pub(crate) fn after_update(&mut self, event_ts: Option<i64>) {
+        self.updated_at = Some(Instant::now());
+        if self.level_ttl_ms.is_some() {
+            self.prune_stale(Utc::now().timestamp_millis());
+        }
+
+        if let Some(ts) = event_ts {
+            self.timestamp = Some(ts);
+            let top = (ts, self.best_size(Side::Bid), self.best_size(Side::Ask));
+            self.top_sizes = [self.top_sizes[1], Some(top)];
+        }
+        self.mids = [self.mids[1], self.mid()];
+        *self.bid_level_counts.entry(self.bids.len()).or_default() += 1;
+        *self.ask_level_counts.entry(self.asks.len()).or_default() += 1;
+
+        if let Some(imbalance) = self.imbalance() {
+            if self.extreme_imbalance.is_none_or(|(_, extreme)| imbalance.abs() > extreme.abs()) {
+                let ts = event_ts.unwrap_or_else(|| Utc::now().timestamp_millis());
+                self.extreme_imbalance = Some((ts, imbalance));
+            }
+        }
+
+        if let Some(spread) = self.spread() {
+            if self.spread_history.len() >= self.spread_history_capacity {
+                self.spread_history.pop_front();
+            }
+            if self.spread_history_capacity > 0 {
+                let ts = event_ts.unwrap_or_else(|| Utc::now().timestamp_millis());
+                self.spread_history.push_back((ts, spread));
+            }
+        }
+
+        if self.snapshot_history_capacity > 0 && self.last_sequence.is_some() {
+            if self.snapshot_history.len() >= self.snapshot_history_capacity {
+                self.snapshot_history.pop_front();
+            }
+            self.snapshot_history.push_back(self.snapshot());
+        }
+    }
+
+    /// Applies an incremental change to a single level.
+    ///
+    /// A positive `size` inserts the level or replaces its size; a size of zero removes it.
+    /// Negative sizes and non-finite prices are ignored. If an insert pushes the side past
+    /// `FeedConfig::max_levels`, the worst-priced level is evicted and a warning is logged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.apply_delta(Side::Bid, 2000.0, 15);
+    /// order_book.apply_delta(Side::Bid, 2000.0, 0);
+    /// assert!(order_book.snapshot().bids.is_empty());
+    /// ```
+    pub fn apply_delta(&mut self, side: Side, price: f64, size: i64) {
+        self.set_level(side, price, size);
+        self.after_update(None);
+    }
+
+    /// Applies a full replacement of the book tagged with `sequence`, unless that sequence was already applied.
+    ///
+    /// After a reconnect, buffered messages of the old connection may interleave with the new one.
+    /// Keying on the sequence makes replaying them a no-op. Returns `true` if the update was applied.
+    pub fn update_sequenced(&mut self, bids: Vec<(f64, i64)>, asks: Vec<(f64, i64)>, sequence: u64) -> bool {
+        if self.last_sequence.is_some_and(|last| sequence <= last) {
+            return false;
+        }
+        self.last_sequence = Some(sequence);
+        self.update(bids, asks);
+        true
+    }
+
+    /// Like [`OrderBook::apply_delta`], but ignores deltas whose `sequence` was already applied.
+    ///
+    /// Returns `true` if the delta was applied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// assert!(order_book.apply_delta_sequenced(Side::Bid, 2000.0, 15, 7));
+    /// assert!(!order_book.apply_delta_sequenced(Side::Bid, 2000.0, 15, 7));
+    /// ```
+    pub fn apply_delta_sequenced(&mut self, side: Side, price: f64, size: i64, sequence: u64) -> bool {
+        if self.last_sequence.is_some_and(|last| sequence <= last) {
+            return false;
+        }
+        self.last_sequence = Some(sequence);
+        self.apply_delta(side, price, size);
+        true
+    }
+
+    /// Inserts, resizes or removes one level without recording per-update history.
+    pub(crate) fn set_level(&mut self, side: Side, price: f64, size: i64) {
+        if !price.is_finite() || size < 0 {
+            return;
+        }
+
+        let item = match &self.price_transform {
+            Some(transform) => Item::new(transform(price), size),
+            None => Item::new(price, size),
+        };
+        let price = item.price;
+        let levels = match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        };
+
+        match levels.binary_search(&item) {
+            Ok(pos) if size == 0 => {
+                levels.remove(pos);
+            }
+            Ok(pos) => levels[pos].size = size,
+            Err(pos) if size > 0 => levels.insert(pos, item),
+            Err(_) => {}
+        }
+
+        let mut evicted_price = None;
+        if levels.len() > self.max_levels {
+            let evicted = match side {
+                Side::Bid => levels.remove(0),
+                Side::Ask => levels.pop().unwrap(),
+            };
+            log::warn!(
+                "⚠️ {:?} side exceeded {} levels, evicting {}",
+                side, self.max_levels, evicted.price
+            );
+            evicted_price = Some(evicted.price);
+        }
+
+        if self.level_ttl_ms.is_some() {
+            if size > 0 {
+                self.touched.insert((side, price.to_bits()), Utc::now().timestamp_millis());
+            } else {
+                self.touched.remove(&(side, price.to_bits()));
+            }
+            if let Some(evicted) = evicted_price {
+                self.touched.remove(&(side, evicted.to_bits()));
+            }
+        }
+    }
+
+    /// Removes levels that have not been set within the configured `FeedConfig::level_ttl` of
+    /// `now` (in milliseconds), e.g. because their removal was missed.
+    ///
+    /// This maintenance pass also runs after every update. Returns the number of levels pruned,
+    /// which is always `0` without a level TTL.
+    pub fn prune_stale(&mut self, now: i64) -> usize {
+        let Some(ttl) = self.level_ttl_ms else {
+            return 0;
+        };
+        let cutoff = now - ttl;
+        let before = self.bids.len() + self.asks.len();
+
+        let touched = &mut self.touched;
+        for (side, levels) in [(Side::Bid, &mut self.bids), (Side::Ask, &mut self.asks)] {
+            levels.retain(|item| {
+                let key = (side, item.price.to_bits());
+                let fresh = touched.get(&key).is_none_or(|&ts| ts >= cutoff);
+                if !fresh {
+                    touched.remove(&key);
+                }
+                fresh
+            });
+        }
+
+        let pruned = before - self.bids.len() - self.asks.len();
+        if pruned > 0 {
+            log::warn!("⚠️ Pruned {} levels untouched for {} ms", pruned, ttl);
+        }
+        pruned
+    }
+
+    /// The best five levels of `side`, best price first, without allocating.
+    ///
+    /// Slots past the depth of the side are `None`, which suits fixed-width displays.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2000.0, 15), (1999.0, 5)], vec![]);
+    /// let best = order_book.best_5(Side::Bid);
+    /// assert_eq!(best[0].as_ref().map(|item| item.price), Some(2000.0));
+    /// assert!(best[2].is_none());
+    /// ```
+    pub fn best_5(&self, side: Side) -> [Option<Item>; 5] {
+        let mut levels = self.best_first(side);
+        std::array::from_fn(|_| levels.next().cloned())
+    }
+
+    /// The bid levels, sorted by ascending price (best bid last).
+    pub fn bids_slice(&self) -> &[Item] {
+        &self.bids
+    }
+
+    /// The ask levels, sorted by ascending price (best ask first).
+    pub fn asks_slice(&self) -> &[Item] {
+        &self.asks
+    }
+
+    /// An estimate of the bytes allocated for the levels, including the per-level TTL bookkeeping.
+    ///
+    /// Counts allocated capacity rather than stored levels, so it stays high after heavy churn
+    /// until [`OrderBook::shrink_to_fit`] is called.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// assert_eq!(order_book.level_memory(), 0);
+    /// order_book.update(vec![(2000.0, 15)], vec![(2010.0, 20)]);
+    /// assert!(order_book.level_memory() > 0);
+    /// ```
+    pub fn level_memory(&self) -> usize {
+        (self.bids.capacity() + self.asks.capacity()) * std::mem::size_of::<Item>()
+            + self.touched.capacity() * std::mem::size_of::<((Side, u64), i64)>()
+    }
+
+    /// Releases the capacity left over by levels that have since been removed.
+    pub fn shrink_to_fit(&mut self) {
+        self.bids.shrink_to_fit();
+        self.asks.shrink_to_fit();
+        self.touched.shrink_to_fit();
+    }
+
+    /// Parses a KuCoin depth message and applies it to the book.
+    ///
+    /// This is the public entry point for feeding the book with your own JSON. Up to `depth()`
+    /// levels are read from each of `data.bids` and `data.asks`, then applied with [`OrderBook::update`].
+    /// The message's `sequenceStart`/`sequenceEnd` (or `sequence`) is checked for continuity and
+    /// reported in [`UpdateReport::sequence`]. Stale messages, which end at or before the last
+    /// applied sequence, arrived out of order and are not applied.
+    ///
+    /// Spot level2 messages carrying `data.changes.bids`/`data.changes.asks` instead are read with
+    /// [`parse_changes_with`] and applied level by level, as with [`OrderBook::apply_delta`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// let msg = serde_json::json!({
+    ///     "data": {"bids": [["2000.0", 15]], "asks": [["2010.0", 20]]}
+    /// });
+    /// let report = order_book.apply_json(&msg);
+    /// assert_eq!(report.accepted, 2);
+    /// ```
+    pub fn apply_json(&mut self, value: &Value) -> UpdateReport {
+        let changes = parse_changes_with(value, self.depth_keys, self.level_layout);
+        let is_delta = changes.is_some();
+        let parsed = changes.unwrap_or_else(|| parse_depth_with(value, self.depth, self.depth_keys, self.level_layout));
+        let sequence = parsed.sequence.map(|range| self.check_sequence(range));
+        if sequence == Some(SequenceCheck::Stale) {
+            return UpdateReport { accepted: 0, rejected: parsed.rejected, sequence };
         }
 
-        for item in asks {
-            self.asks.insert(Item {price: item.0, size: item.1});
+        let report = UpdateReport {
+            accepted: parsed.bids.len() + parsed.asks.len(),
+            rejected: parsed.rejected,
+            sequence,
+        };
+
+        if is_delta {
+            for (price, size) in parsed.bids {
+                self.set_level(Side::Bid, price, size);
+            }
+            for (price, size) in parsed.asks {
+                self.set_level(Side::Ask, price, size);
+            }
+        } else {
+            self.replace_levels(parsed.bids, parsed.asks);
+        }
+        if let Some(range) = parsed.sequence {
+            self.last_sequence = Some(range.end);
+        }
+        self.after_update(parsed.timestamp);
+        report
+    }
+
+    /// Applies `messages` in order with [`OrderBook::apply_json`], e.g. to replay a recording.
+    ///
+    /// Returns one report per message, in the same order. Each message's sequence is checked
+    /// against the ones applied before it, so stale messages within the batch are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// let reports = order_book.apply_batch(&[
+    ///     serde_json::json!({"data": {"bids": [["2000.0", 15]], "asks": []}}),
+    ///     serde_json::json!({"data": {"bids": [["2001.0", 5]], "asks": []}}),
+    /// ]);
+    /// assert_eq!(reports.len(), 2);
+    /// assert_eq!(order_book.best_bid(), Some(2001.0));
+    /// ```
+    pub fn apply_batch(&mut self, messages: &[Value]) -> Vec<UpdateReport> {
+        messages.iter().map(|message| self.apply_json(message)).collect()
+    }
+
+    /// Records the price of a trade from a KuCoin execution (match) message.
+    ///
+    /// Returns `true` if the message carried a readable `data.price`.
+    ///
+    /// # Example JSON Input
+    ///
+    /// ```json
+    /// {
+    ///   "topic": "/contractMarket/execution:ETHUSDTM",
+    ///   "type": "message",
+    ///   "subject": "match",
+    ///   "data": {"symbol": "ETHUSDTM", "side": "buy", "size": 3, "price": "2678.36", "ts": 1739942942550000000}
+    /// }
+    /// ```
+    pub fn apply_execution(&mut self, value: &Value) -> bool {
+        let data = &value["data"];
+        let Some(price) = coerce_f64(&data["price"]) else {
+            return false;
+        };
+
+        self.last_trade = Some(price);
+        if let Some(size) = coerce_i64(&data["size"]) {
+            let ts = parse_timestamp(data).unwrap_or_else(|| Utc::now().timestamp_millis());
+            self.record_trade(price, size, ts);
+        }
+        true
+    }
+
+    /// Adds a trade of `size` contracts at `price`, executed at `ts` (in milliseconds), to the VWAP window.
+    ///
+    /// Trades older than `FeedConfig::trade_vwap_window` relative to `ts` are evicted.
+    pub fn record_trade(&mut self, price: f64, size: i64, ts: i64) {
+        self.trades.push_back((price, size, ts));
+        let cutoff = ts - self.trade_window_ms;
+        while self.trades.front().is_some_and(|&(_, _, trade_ts)| trade_ts <= cutoff) {
+            self.trades.pop_front();
+        }
+    }
+
+    /// The volume-weighted average price of the trades within the window ending at `now` (in milliseconds).
+    ///
+    /// Returns `None` when no trade falls within the window.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// order_book.record_trade(2000.0, 1, 1_000);
+    /// order_book.record_trade(2010.0, 3, 2_000);
+    /// assert_eq!(order_book.trade_vwap(2_000), Some(2007.5));
+    /// ```
+    pub fn trade_vwap(&self, now: i64) -> Option<f64> {
+        let cutoff = now - self.trade_window_ms;
+        let (notional, size) = self
+            .trades
+            .iter()
+            .filter(|&&(_, _, ts)| ts > cutoff && ts <= now)
+            .fold((0.0, 0), |(notional, size), &(price, trade_size, _)| {
+                (notional + price * trade_size as f64, size + trade_size)
+            });
+
+        if size == 0 {
+            return None;
+        }
+        Some(notional / size as f64)
+    }
+
+    /// The price of the last trade seen on the execution channel.
+    pub fn last_trade(&self) -> Option<f64> {
+        self.last_trade
+    }
+
+    /// Renders the current state of the order book as a table.
+    ///
+    /// Uses prettytable when the `pretty` feature is enabled (the default), and a plain-text
+    /// layout from [`crate::format::plain_table`] otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// # let order_book = OrderBook::new();
+    /// let table = order_book.render_table();
+    /// assert!(table.contains("Contract size"));
+    /// ```
+    pub fn render_table(&self) -> String {
+        let rows = self.table_rows();
+
+        #[cfg(feature = "pretty")]
+        {
+            self.styled_table(&rows).to_string()
+        }
+
+        #[cfg(not(feature = "pretty"))]
+        {
+            plain_table(&rows)
+        }
+    }
+
+    /// Builds a prettytable of `rows`, styling each level's cells with [`level_style`].
+    ///
+    /// The styles only show up when the table is printed to a terminal, see [`OrderBook::print`].
+    #[cfg(feature = "pretty")]
+    fn styled_table(&self, rows: &[Vec<String>]) -> Table {
+        let levels = self
+            .bids
+            .iter()
+            .map(|item| (Side::Bid, item.size))
+            .chain(self.asks.iter().rev().map(|item| (Side::Ask, item.size)));
+
+        let mut table = Table::new();
+        let (header, level_rows) = rows.split_first().expect("table rows start with a header");
+        table.add_row(Row::new(header.iter().map(|cell| Cell::new(cell)).collect()));
+
+        for (row, (side, size)) in level_rows.iter().zip(levels) {
+            let style = level_style(side, size, self.wall_threshold);
+            table.add_row(Row::new(
+                row.iter().map(|cell| Cell::new(cell).style_spec(style)).collect(),
+            ));
+        }
+        table
+    }
+
+    /// Renders the book in the requested [`OutputFormat`].
+    ///
+    /// * `Table` - the header line followed by [`OrderBook::render_table`].
+    /// * `Json` - the [`OrderBookSnapshot`] serialized on a single line.
+    /// * `Compact` - a single line such as `BBO 1999.99/2000.01 spread 0.02`.
+    /// * `Ticker` - a single line such as `bid 1999.99 ask 2000.01 spread 0.02 mid 2000.000`.
+    ///
+    /// Every format ends with a newline.
+    pub fn render(&self, format: OutputFormat) -> String {
+        match format {
+            OutputFormat::Table => format!(
+                "{}\n{}",
+                header_line(Utc::now(), self.timestamp),
+                self.render_table()
+            ),
+            OutputFormat::Json => format!("{}\n", self.snapshot().to_json()),
+            OutputFormat::Compact => format!("{}\n", compact_line(self.best_bid(), self.best_ask())),
+            OutputFormat::Ticker => format!("{}\n", ticker_line(self.best_bid(), self.best_ask())),
         }
     }
 
     /// Prints the current state of the order book in a columnar format.
     ///
-    /// Displays the top 5 bids (highest prices) and top 5 asks (lowest prices).
+    /// Displays a header with the current UTC time and the event timestamp (see [`header_line`]),
+    /// then the bids followed by the asks, as rendered by [`OrderBook::render_table`].
+    ///
+    /// With the `pretty` feature, bids are printed in green and asks in red when stdout is a terminal,
+    /// and levels larger than `PrintConfig::wall_threshold` are highlighted.
     ///
     /// # Examples
     ///
     /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// # let order_book = OrderBook::new();
     /// order_book.print();
     /// ```
     pub fn print(&self) {
-        let mut table = Table::new();
-        table.add_row(Row::new(vec![
-            Cell::new("Type"),
-            Cell::new("Symbol"),
-            Cell::new("Price"),
-            Cell::new("Contract size"),
-        ]));
-
-        for item in &self.bids {
-            table.add_row(Row::new(vec![
-                Cell::new("Bids"),
-                Cell::new("ETHUSDTM"),
-                Cell::new(&item.price.to_string()),
-                Cell::new(&item.size.to_string()),
-            ]));
-        }
-
-        for item in self.asks.iter().rev() {
-            table.add_row(Row::new(vec![
-                Cell::new("Asks"),
-                Cell::new("ETHUSDTM"),
-                Cell::new(&item.price.to_string()),
-                Cell::new(&item.size.to_string()),
-            ]));
-        }
-        print!("Current order book state\n");
-        table.printstd();
-    }
-
-}
\ No newline at end of file
+        #[cfg(feature = "pretty")]
+        {
+            println!("{}", header_line(Utc::now(), self.timestamp));
+            self.styled_table(&self.table_rows()).printstd();
+        }
+
+        #[cfg(not(feature = "pretty"))]
+        {
+            print!("{}", self.render(OutputFormat::Table));
+        }
+    }
+
+    /// Builds the header row and one row per level, in display order.
+    fn table_rows(&self) -> Vec<Vec<String>> {
+        let mut rows = vec![vec![
+            "Type".to_string(),
+            "Symbol".to_string(),
+            "Price".to_string(),
+            "Contract size".to_string(),
+        ]];
+
+        let levels: Vec<(&str, &Item)> = self
+            .bids
+            .iter()
+            .map(|item| ("Bids", item))
+            .chain(self.asks.iter().rev().map(|item| ("Asks", item)))
+            .collect();
+        let sizes: Vec<f64> = levels.iter().map(|(_, item)| item.size as f64).collect();
+
+        for ((kind, item), size) in levels.iter().zip(align_decimals(&sizes)) {
+            rows.push(vec![
+                kind.to_string(),
+                self.symbol.to_string(),
+                item.price.to_string(),
+                size,
+            ]);
+        }
+        rows
+    }
+
+    /// Takes a copy of the current book, best price first on both sides.
+    pub fn snapshot(&self) -> OrderBookSnapshot {
+        OrderBookSnapshot {
+            symbol: self.symbol.to_string(),
+            bids: self.bids.iter().rev().map(|item| (item.price, item.size)).collect(),
+            asks: self.asks.iter().map(|item| (item.price, item.size)).collect(),
+            timestamp: self.timestamp,
+            sequence: self.last_sequence,
+        }
+    }
+
+    /// Serializes the book in the shape of a KuCoin depth message, to re-emit it to consumers
+    /// that expect KuCoin's format.
+    ///
+    /// Levels are `["price", "size"]` string pairs, best price first, under `data.bids` and
+    /// `data.asks`, alongside the book's `data.timestamp` (or `null`). Feeding the result back
+    /// into [`OrderBook::apply_json`] reproduces the book.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2000.5, 15)], vec![(2010.0, 20)]);
+    /// assert_eq!(
+    ///     order_book.to_kucoin_value().to_string(),
+    ///     r#"{"data":{"asks":[["2010","20"]],"bids":[["2000.5","15"]],"timestamp":null}}"#
+    /// );
+    /// ```
+    pub fn to_kucoin_value(&self) -> Value {
+        let levels = |side: Side| -> Vec<[String; 2]> {
+            self.best_first(side)
+                .map(|item| [item.price.to_string(), item.size.to_string()])
+                .collect()
+        };
+
+        serde_json::json!({
+            "data": {
+                "bids": levels(Side::Bid),
+                "asks": levels(Side::Ask),
+                "timestamp": self.timestamp,
+            }
+        })
+    }
+
+    /// Copies the levels of one side, best price first, as in [`OrderBook::snapshot`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(1999.0, 5), (2000.0, 15)], vec![]);
+    /// assert_eq!(order_book.side_snapshot(Side::Bid), vec![(2000.0, 15), (1999.0, 5)]);
+    /// assert!(order_book.side_snapshot(Side::Ask).is_empty());
+    /// ```
+    pub fn side_snapshot(&self, side: Side) -> Vec<(f64, i64)> {
+        self.best_first(side).map(|item| (item.price, item.size)).collect()
+    }
+
+    /// Classifies the book by which sides hold levels.
+    ///
+    /// Levels with a size of zero are never stored, so a feed sending only zero sizes leaves the
+    /// book [`LiquidityState::Empty`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{LiquidityState, OrderBook};
+    /// let mut order_book = OrderBook::new();
+    /// assert_eq!(order_book.liquidity_state(), LiquidityState::Empty);
+    /// order_book.update(vec![(2000.0, 15)], vec![]);
+    /// assert_eq!(order_book.liquidity_state(), LiquidityState::OneSided);
+    /// ```
+    pub fn liquidity_state(&self) -> LiquidityState {
+        match (self.bids.is_empty(), self.asks.is_empty()) {
+            (false, false) => LiquidityState::Normal,
+            (true, true) => LiquidityState::Empty,
+            _ => LiquidityState::OneSided,
+        }
+    }
+
+    /// The side without any level when only the other side has liquidity, e.g. to warn "no asks".
+    ///
+    /// Returns `None` when both sides hold levels and when the book is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2000.0, 15)], vec![]);
+    /// assert_eq!(order_book.missing_side(), Some(Side::Ask));
+    /// ```
+    pub fn missing_side(&self) -> Option<Side> {
+        match (self.bids.is_empty(), self.asks.is_empty()) {
+            (true, false) => Some(Side::Bid),
+            (false, true) => Some(Side::Ask),
+            _ => None,
+        }
+    }
+
+    /// The level of `side` whose price is closest to `target`, or `None` if the side is empty.
+    ///
+    /// The neighbors of `target` are found with a binary search. When `target` lies exactly halfway
+    /// between two levels, the lower price is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(1990.0, 5), (2000.0, 15)], vec![]);
+    /// assert_eq!(order_book.nearest_level(Side::Bid, 1998.0).unwrap().price, 2000.0);
+    /// ```
+    pub fn nearest_level(&self, side: Side, target: f64) -> Option<&Item> {
+        let levels = match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        };
+
+        let above = levels.partition_point(|item| item.price < target);
+        let below = above.checked_sub(1).map(|i| &levels[i]);
+        match (below, levels.get(above)) {
+            (Some(low), Some(high)) if high.price - target < target - low.price => Some(high),
+            (Some(low), _) => Some(low),
+            (None, high) => high,
+        }
+    }
+
+    /// The bids and asks whose prices fall within `[low, high]`, both bounds included.
+    ///
+    /// Each side is returned by ascending price. The band edges are found with a binary search, so
+    /// zooming in on a deep book does not scan every level.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(1990.0, 5), (2000.0, 15)], vec![(2001.0, 3), (2050.0, 1)]);
+    /// let (bids, asks) = order_book.levels_in_range(1995.0, 2010.0);
+    /// assert_eq!((bids.len(), asks.len()), (1, 1));
+    /// ```
+    pub fn levels_in_range(&self, low: f64, high: f64) -> (Vec<&Item>, Vec<&Item>) {
+        (
+            price_band(&self.bids, low, high).iter().collect(),
+            price_band(&self.asks, low, high).iter().collect(),
+        )
+    }
+
+    /// Exports every level as an Arrow record batch with `side`, `price` and `size` columns.
+    ///
+    /// `side` is `"bid"` or `"ask"`. Bids come first, then asks, each best price first as in
+    /// [`OrderBook::snapshot`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2000.0, 15)], vec![(2010.0, 20)]);
+    /// assert_eq!(order_book.to_arrow().num_rows(), 2);
+    /// ```
+    #[cfg(feature = "arrow")]
+    pub fn to_arrow(&self) -> RecordBatch {
+        let levels: Vec<(&str, &Item)> = self
+            .best_first(Side::Bid)
+            .map(|item| ("bid", item))
+            .chain(self.best_first(Side::Ask).map(|item| ("ask", item)))
+            .collect();
+
+        let schema = Schema::new(vec![
+            Field::new("side", DataType::Utf8, false),
+            Field::new("price", DataType::Float64, false),
+            Field::new("size", DataType::Int64, false),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(levels.iter().map(|(side, _)| *side))),
+            Arc::new(Float64Array::from_iter_values(levels.iter().map(|(_, item)| item.price))),
+            Arc::new(Int64Array::from_iter_values(levels.iter().map(|(_, item)| item.size))),
+        ];
+        RecordBatch::try_new(Arc::new(schema), columns).expect("columns match the schema")
+    }
+
+    /// The highest bid price.
+    pub fn best_bid(&self) -> Option<f64> {
+        self.bids.last().map(|item| item.price)
+    }
+
+    /// The lowest ask price.
+    pub fn best_ask(&self) -> Option<f64> {
+        self.asks.first().map(|item| item.price)
+    }
+
+    /// The difference between the best ask and the best bid.
+    pub fn spread(&self) -> Option<f64> {
+        Some(self.best_ask()? - self.best_bid()?)
+    }
+
+    /// Returns `true` if the best bid is at or above the best ask, which a consistent book never is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2010.0, 15)], vec![(2000.0, 20)]);
+    /// assert!(order_book.is_crossed());
+    /// ```
+    pub fn is_crossed(&self) -> bool {
+        self.spread().is_some_and(|spread| spread <= 0.0)
+    }
+
+    /// The average of the best bid and the best ask.
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.best_ask()? + self.best_bid()?) / 2.0)
+    }
+
+    /// The relative change of the mid since the previous update: `(mid - previous mid) / previous mid`.
+    ///
+    /// Returns `None` until two updates have been applied, or if either of them left a side empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(1999.0, 1)], vec![(2001.0, 1)]);
+    /// assert_eq!(order_book.mid_return(), None);
+    /// order_book.update(vec![(2019.0, 1)], vec![(2021.0, 1)]);
+    /// assert_eq!(order_book.mid_return(), Some(0.01));
+    /// ```
+    pub fn mid_return(&self) -> Option<f64> {
+        let previous = self.mids[0].filter(|&mid| mid != 0.0)?;
+        Some((self.mids[1]? - previous) / previous)
+    }
+
+    /// The order book imbalance across every level: `(bid size - ask size) / (bid size + ask size)`.
+    ///
+    /// Ranges from `-1.0` (only asks) to `1.0` (only bids). Returns `None` for an empty book.
+    pub fn imbalance(&self) -> Option<f64> {
+        let bid_size: i64 = self.bids.iter().map(|item| item.size).sum();
+        let ask_size: i64 = self.asks.iter().map(|item| item.size).sum();
+        let total = bid_size + ask_size;
+        if total == 0 {
+            return None;
+        }
+        Some((bid_size - ask_size) as f64 / total as f64)
+    }
+
+    /// The `(timestamp, imbalance)` of the most one-sided book seen this session, i.e. the
+    /// [`OrderBook::imbalance`] furthest from zero after any update.
+    ///
+    /// The timestamp is the event time of the update when it carried one, otherwise the local
+    /// time in milliseconds. The first update to reach an extreme keeps it; ties do not replace it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(1999.0, 3)], vec![(2001.0, 1)]);
+    /// order_book.update(vec![(1999.0, 1)], vec![(2001.0, 9)]);
+    /// order_book.update(vec![(1999.0, 1)], vec![(2001.0, 1)]);
+    /// assert_eq!(order_book.extreme_imbalance().map(|(_, imbalance)| imbalance), Some(-0.8));
+    /// ```
+    pub fn extreme_imbalance(&self) -> Option<(i64, f64)> {
+        self.extreme_imbalance
+    }
+
+    /// Near-touch pressure: the [`OrderBook::imbalance`] of only the best `depth` levels of each side.
+    ///
+    /// Returns `None` when those levels hold no size, including for a `depth` of `0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2000.0, 30), (1999.0, 1)], vec![(2001.0, 10), (2002.0, 100)]);
+    /// assert_eq!(order_book.pressure(1), Some(0.5));
+    /// ```
+    pub fn pressure(&self, depth: usize) -> Option<f64> {
+        let bid_size: i64 = self.best_first(Side::Bid).take(depth).map(|item| item.size).sum();
+        let ask_size: i64 = self.best_first(Side::Ask).take(depth).map(|item| item.size).sum();
+        let total = bid_size + ask_size;
+        if total == 0 {
+            return None;
+        }
+        Some((bid_size - ask_size) as f64 / total as f64)
+    }
+
+    /// The average size per level of `side`: its total size divided by its level count.
+    ///
+    /// Returns `None` for an empty side.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2000.0, 15), (1999.0, 5)], vec![]);
+    /// assert_eq!(order_book.avg_order_size(Side::Bid), Some(10.0));
+    /// assert_eq!(order_book.avg_order_size(Side::Ask), None);
+    /// ```
+    pub fn avg_order_size(&self, side: Side) -> Option<f64> {
+        let levels = match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        };
+        if levels.is_empty() {
+            return None;
+        }
+        let total: i64 = levels.iter().map(|item| item.size).sum();
+        Some(total as f64 / levels.len() as f64)
+    }
+
+    /// The size-weighted average price of every level of both sides combined.
+    ///
+    /// Unlike the mid, which only looks at the touch, deep liquidity on one side pulls the center
+    /// of mass towards it. Returns `None` for an empty book.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2000.0, 30)], vec![(2010.0, 10)]);
+    /// assert_eq!(order_book.center_of_mass(), Some(2002.5));
+    /// ```
+    pub fn center_of_mass(&self) -> Option<f64> {
+        let levels = self.bids.iter().chain(&self.asks);
+        let total: i64 = levels.clone().map(|item| item.size).sum();
+        if total == 0 {
+            return None;
+        }
+        let notional: f64 = levels.map(|item| item.price * item.size as f64).sum();
+        Some(notional / total as f64)
+    }
+
+    /// The Shannon entropy, in nats, of the sizes of `side` taken as a probability distribution.
+    ///
+    /// Liquidity spread evenly over `n` levels has the maximum entropy `ln(n)`; liquidity
+    /// concentrated in a single level has an entropy of `0`. Returns `None` for an empty side.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2000.0, 15)], vec![(2001.0, 5), (2002.0, 5)]);
+    /// assert_eq!(order_book.entropy(Side::Bid), Some(0.0));
+    /// assert_eq!(order_book.entropy(Side::Ask), Some(2f64.ln()));
+    /// ```
+    pub fn entropy(&self, side: Side) -> Option<f64> {
+        let total: i64 = self.best_first(side).map(|item| item.size).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let entropy = self
+            .best_first(side)
+            .map(|item| item.size as f64 / total as f64)
+            .map(|p| -p * p.ln())
+            .sum::<f64>();
+        // A single level yields `-0.0`
+        Some(entropy.abs())
+    }
+
+    /// Returns `true` when the absolute [`OrderBook::imbalance`] is within `tolerance`.
+    ///
+    /// An empty book, whose imbalance is undefined, is never balanced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2000.0, 11)], vec![(2010.0, 9)]);
+    /// assert!(order_book.is_balanced(0.1));
+    /// assert!(!order_book.is_balanced(0.05));
+    /// ```
+    pub fn is_balanced(&self, tolerance: f64) -> bool {
+        self.imbalance().is_some_and(|imbalance| imbalance.abs() <= tolerance)
+    }
+
+    /// A liquidity concentration metric: the sum of `size / (1 + |price - mid|)` over every level.
+    ///
+    /// Depth close to the mid counts for more than depth far from it. Returns `None` when the
+    /// mid is undefined, i.e. when either side is empty.
+    pub fn concentration(&self) -> Option<f64> {
+        let mid = self.mid()?;
+        Some(
+            self.bids
+                .iter()
+                .chain(&self.asks)
+                .map(|item| item.size as f64 / (1.0 + (item.price - mid).abs()))
+                .sum(),
+        )
+    }
+
+    /// How many updates left `side` with each number of levels, keyed by level count.
+    ///
+    /// Counted after every update since the book was created, this shows whether a feed
+    /// consistently delivers its full depth or sometimes fewer levels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2000.0, 15), (1999.0, 5)], vec![]);
+    /// order_book.update(vec![(2000.0, 15)], vec![]);
+    /// order_book.update(vec![(2000.0, 10), (1999.0, 5)], vec![]);
+    /// let histogram = order_book.price_levels_count_histogram(Side::Bid);
+    /// assert_eq!(histogram.get(&2), Some(&2));
+    /// assert_eq!(histogram.get(&1), Some(&1));
+    /// ```
+    pub fn price_levels_count_histogram(&self, side: Side) -> &BTreeMap<usize, u64> {
+        match side {
+            Side::Bid => &self.bid_level_counts,
+            Side::Ask => &self.ask_level_counts,
+        }
+    }
+
+    /// The most recent `(timestamp, spread)` samples, oldest first.
+    ///
+    /// A sample is recorded after every update where both sides are present. Timestamps are the
+    /// message's event timestamp in milliseconds, or the local time for [`OrderBook::update`].
+    /// At most `FeedConfig::spread_history` samples are retained.
+    ///
+    /// Takes `&mut self` to rearrange the ring buffer into one slice, which is only done here
+    /// rather than on every update.
+    pub fn peek_spread_history(&mut self) -> &[(i64, f64)] {
+        self.spread_history.make_contiguous()
+    }
+
+    /// The snapshot recorded right after the update ending at `sequence` was applied, e.g. to
+    /// inspect the book around a desync.
+    ///
+    /// Returns `None` if no such update was applied, or its snapshot is no longer among the
+    /// `FeedConfig::snapshot_history` most recent ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::config::FeedConfig;
+    /// # use warmup_project::order_book::OrderBook;
+    /// let mut order_book = OrderBook::with_config(&FeedConfig { snapshot_history: 10, ..FeedConfig::default() });
+    /// order_book.update_sequenced(vec![(2000.0, 15)], vec![], 7);
+    /// assert_eq!(order_book.snapshot_at_sequence(7).unwrap().bids, vec![(2000.0, 15)]);
+    /// assert!(order_book.snapshot_at_sequence(8).is_none());
+    /// ```
+    pub fn snapshot_at_sequence(&self, sequence: u64) -> Option<&OrderBookSnapshot> {
+        self.snapshot_history.iter().find(|snapshot| snapshot.sequence == Some(sequence))
+    }
+
+    /// Returns each level's price paired with the running total size, starting from the best price.
+    ///
+    /// Bids accumulate from the highest price downward, asks from the lowest price upward.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2000.0, 15), (1999.0, 5)], vec![]);
+    /// assert_eq!(order_book.cumulative(Side::Bid), vec![(2000.0, 15), (1999.0, 20)]);
+    /// ```
+    pub fn cumulative(&self, side: Side) -> Vec<(f64, i64)> {
+        let mut total = 0;
+        self.best_first(side)
+            .map(|item| {
+                total += item.size;
+                (item.price, total)
+            })
+            .collect()
+    }
+
+    /// A local linear price-impact estimate: the change in price per contract of cumulative size.
+    ///
+    /// Fits `price = a + slope * cumulative size` by least squares over the [`OrderBook::cumulative`]
+    /// curve of `side`, using the levels whose cumulative size is at most `max_size`. The slope is
+    /// positive on the asks and negative on the bids. Returns `None` when fewer than two levels qualify.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![], vec![(2000.0, 10), (2001.0, 10), (2002.0, 10)]);
+    /// assert_eq!(order_book.slope(Side::Ask, 30), Some(0.1));
+    /// ```
+    pub fn slope(&self, side: Side, max_size: i64) -> Option<f64> {
+        let points: Vec<(f64, f64)> = self
+            .cumulative(side)
+            .into_iter()
+            .take_while(|&(_, total)| total <= max_size)
+            .map(|(price, total)| (total as f64, price))
+            .collect();
+        if points.len() < 2 {
+            return None;
+        }
+
+        let n = points.len() as f64;
+        let mean_size = points.iter().map(|&(size, _)| size).sum::<f64>() / n;
+        let mean_price = points.iter().map(|&(_, price)| price).sum::<f64>() / n;
+        let covariance: f64 = points.iter().map(|&(size, price)| (size - mean_size) * (price - mean_price)).sum();
+        let variance: f64 = points.iter().map(|&(size, _)| (size - mean_size).powi(2)).sum();
+        Some(covariance / variance)
+    }
+
+    /// The average price of filling `size` contracts against the levels of `side`, best price first.
+    ///
+    /// Walking the asks prices a market buy, walking the bids a market sell. Returns `None` if `size`
+    /// is not positive or the side does not hold enough depth.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![], vec![(2000.0, 1), (2004.0, 3)]);
+    /// assert_eq!(order_book.price_to_fill(Side::Ask, 2), Some(2002.0));
+    /// assert_eq!(order_book.price_to_fill(Side::Ask, 5), None);
+    /// ```
+    pub fn price_to_fill(&self, side: Side, size: i64) -> Option<f64> {
+        if size <= 0 {
+            return None;
+        }
+
+        let mut remaining = size;
+        let mut notional = 0.0;
+        for item in self.best_first(side) {
+            let taken = remaining.min(item.size);
+            notional += item.price * taken as f64;
+            remaining -= taken;
+            if remaining == 0 {
+                return Some(notional / size as f64);
+            }
+        }
+        None
+    }
+
+    /// A copy of the book with the best `size` contracts of `side` removed, simulating the
+    /// immediate impact of a market order.
+    ///
+    /// Sweeping the asks simulates a market buy, sweeping the bids a market sell. Fully consumed
+    /// levels disappear and the last level touched keeps its unfilled remainder. A `size` larger
+    /// than the side empties it. The other side and `self` are left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![], vec![(2000.0, 1), (2004.0, 3)]);
+    /// let swept = order_book.after_sweep(Side::Ask, 2);
+    /// assert_eq!(swept.snapshot().asks, vec![(2004.0, 2)]);
+    /// ```
+    pub fn after_sweep(&self, side: Side, size: i64) -> OrderBook {
+        let config = FeedConfig {
+            symbol: self.symbol.clone(),
+            depth: self.depth,
+            max_levels: self.max_levels,
+            ..FeedConfig::default()
+        };
+        let mut swept = OrderBook::with_config(&config);
+        swept.multiplier = self.multiplier;
+        swept.timestamp = self.timestamp;
+        swept.last_sequence = self.last_sequence;
+        swept.bids = self.bids.clone();
+        swept.asks = self.asks.clone();
+
+        let levels = match side {
+            Side::Bid => &mut swept.bids,
+            Side::Ask => &mut swept.asks,
+        };
+        let mut remaining = size.max(0);
+        while remaining > 0 {
+            let best = match side {
+                Side::Bid => levels.last_mut(),
+                Side::Ask => levels.first_mut(),
+            };
+            let Some(best) = best else { break };
+
+            let taken = remaining.min(best.size);
+            best.size -= taken;
+            remaining -= taken;
+            if best.size == 0 {
+                match side {
+                    Side::Bid => levels.pop(),
+                    Side::Ask => Some(levels.remove(0)),
+                };
+            }
+        }
+        swept
+    }
+
+    /// The spread actually paid to buy and then sell `size` contracts, walking as many levels as needed.
+    ///
+    /// For a size that fits in the best levels this equals [`OrderBook::spread`]. Returns `None` if
+    /// either side lacks the depth, see [`OrderBook::price_to_fill`].
+    pub fn effective_spread(&self, size: i64) -> Option<f64> {
+        Some(self.price_to_fill(Side::Ask, size)? - self.price_to_fill(Side::Bid, size)?)
+    }
+
+    /// The total size resting within `bps` basis points of the best price on `side`.
+    ///
+    /// A bid level counts if its price is at least `best * (1 - bps / 10_000)`, an ask level if it
+    /// is at most `best * (1 + bps / 10_000)`. The best level always counts; an empty side holds `0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2000.0, 10), (1999.0, 5), (1990.0, 50)], vec![]);
+    /// assert_eq!(order_book.depth_within_bps(Side::Bid, 10.0), 15);
+    /// ```
+    pub fn depth_within_bps(&self, side: Side, bps: f64) -> i64 {
+        let Some(best) = self.best_first(side).next().map(|item| item.price) else {
+            return 0;
+        };
+        let offset = best * bps / 10_000.0;
+        self.best_first(side)
+            .take_while(|item| match side {
+                Side::Bid => item.price >= best - offset,
+                Side::Ask => item.price <= best + offset,
+            })
+            .map(|item| item.size)
+            .sum()
+    }
+
+    /// The prices on the `tick_size` grid that hold no level between the best and the worst level
+    /// of `side`, best first.
+    ///
+    /// Gaps are measured between neighboring levels, so prices off the grid do not shift the
+    /// expected ticks of the rest of the side. Returns an empty list for a side with fewer than
+    /// two levels, or a `tick_size` that is not positive and finite. At most [`MAX_TICK_GAPS`]
+    /// gaps, the best ones, are returned; a warning is logged when more were found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::order_book::{OrderBook, Side};
+    /// let mut order_book = OrderBook::new();
+    /// order_book.update(vec![(2000.0, 10), (1999.0, 5), (1996.0, 50)], vec![]);
+    /// assert_eq!(order_book.tick_gaps(Side::Bid, 1.0), vec![1998.0, 1997.0]);
+    /// ```
+    pub fn tick_gaps(&self, side: Side, tick_size: f64) -> Vec<f64> {
+        if !(tick_size > 0.0 && tick_size.is_finite()) {
+            return vec![];
+        }
+        let step = match side {
+            Side::Bid => -tick_size,
+            Side::Ask => tick_size,
+        };
+
+        let mut gaps = vec![];
+        for (near, far) in self.best_first(side).zip(self.best_first(side).skip(1)) {
+            let ticks = ((far.price - near.price) / step).round() as i64;
+            let room = MAX_TICK_GAPS - gaps.len();
+            if ticks.saturating_sub(1) > room as i64 {
+                log::warn!("⚠️ More than {} tick gaps of {} on the {:?} side, truncating", MAX_TICK_GAPS, tick_size, side);
+                gaps.extend((1..=room as i64).map(|tick| near.price + step * tick as f64));
+                break;
+            }
+            gaps.extend((1..ticks).map(|tick| near.price + step * tick as f64));
+        }
+        gaps
+    }
+
+    /// Returns the per-second change in best bid and best ask size between the last two timestamped updates.
+    ///
+    /// A missing side counts as size zero. Returns `None` until two updates with distinct
+    /// timestamps have been applied with [`OrderBook::apply_json`].
+    pub fn top_size_velocity(&self) -> Option<(f64, f64)> {
+        let (prev_ts, prev_bid, prev_ask) = self.top_sizes[0]?;
+        let (ts, bid, ask) = self.top_sizes[1]?;
+        if ts <= prev_ts {
+            return None;
+        }
+
+        let seconds = (ts - prev_ts) as f64 / 1000.0;
+        Some(((bid - prev_bid) as f64 / seconds, (ask - prev_ask) as f64 / seconds))
+    }
+
+    /// The size of the best level on `side`, or zero if the side is empty.
+    fn best_size(&self, side: Side) -> i64 {
+        self.best_first(side).next().map_or(0, |item| item.size)
+    }
+
+    /// Iterates the levels of one side, starting from the best price.
+    fn best_first(&self, side: Side) -> Box<dyn Iterator<Item = &Item> + '_> {
+        match side {
+            Side::Bid => Box::new(self.bids.iter().rev()),
+            Side::Ask => Box::new(self.asks.iter()),
+        }
+    }
+
+}
+
+/// Returns `true` for levels worth storing: a finite price and a positive size.
+fn is_valid_level(level: &(f64, i64)) -> bool {
+    level.0.is_finite() && level.1 > 0
+}
+
+/// Replaces the levels of one side with the valid `levels`, sorted by ascending price without duplicates.
+fn refill_side(side: &mut Vec<Item>, levels: Vec<(f64, i64)>, transform: impl Fn(f64) -> f64) {
+    side.clear();
+    side.extend(
+        levels
+            .into_iter()
+            .filter(is_valid_level)
+            .map(|(price, size)| Item::new(transform(price), size)),
+    );
+    // A stable sort keeps duplicates in arrival order, so `dedup` retains the first one
+    side.sort_by(|a, b| a.price.total_cmp(&b.price));
+    side.dedup_by(|next, kept| next.price.total_cmp(&kept.price).is_eq());
+}
+
+/// Merges two price-sorted sides into one, summing the sizes of levels at the same price.
+fn merge_sides(a: &[Item], b: &[Item]) -> Vec<Item> {
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    let (mut a, mut b) = (a.iter().peekable(), b.iter().peekable());
+    loop {
+        let item = match (a.peek(), b.peek()) {
+            (Some(x), Some(y)) => match x.price.total_cmp(&y.price) {
+                Ordering::Less => a.next().cloned(),
+                Ordering::Greater => b.next().cloned(),
+                Ordering::Equal => {
+                    let size = x.size + y.size;
+                    let price = x.price;
+                    a.next();
+                    b.next();
+                    Some(Item { price, size })
+                }
+            },
+            (Some(_), None) => a.next().cloned(),
+            (None, Some(_)) => b.next().cloned(),
+            (None, None) => None,
+        };
+        match item {
+            Some(item) => merged.push(item),
+            None => return merged,
+        }
+    }
+}
+
+/// The levels of a price-sorted side whose prices fall within `[low, high]`.
+fn price_band(levels: &[Item], low: f64, high: f64) -> &[Item] {
+    let start = levels.partition_point(|item| item.price < low);
+    let end = levels.partition_point(|item| item.price <= high);
+    &levels[start..end.max(start)]
+}
+
+/// Two books are equal when they track the same symbol and hold the same levels on both sides.
+///
+/// Derived state such as timestamps, sequences, trades and spread history is not compared.
+impl PartialEq for OrderBook {
+    fn eq(&self, other: &Self) -> bool {
+        self.symbol == other.symbol && self.bids == other.bids && self.asks == other.asks
+    }
+}
+
+impl fmt::Debug for OrderBook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OrderBook")
+            .field("symbol", &self.symbol)
+            .field("bids", &self.bids)
+            .field("asks", &self.asks)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for OrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds a default book holding only `bids`, applied as with [`OrderBook::update`].
+impl From<Bids> for OrderBook {
+    fn from(bids: Bids) -> Self {
+        let mut ob = OrderBook::new();
+        ob.update(bids.0, Vec::new());
+        ob
+    }
+}
+
+/// Builds a default book holding only `asks`, applied as with [`OrderBook::update`].
+impl From<Asks> for OrderBook {
+    fn from(asks: Asks) -> Self {
+        let mut ob = OrderBook::new();
+        ob.update(Vec::new(), asks.0);
+        ob
+    }
+}
@@ -0,0 +1,356 @@
+use serde_json::Value;
+use crate::error::FeedError;
+
+/// The range of sequence numbers covered by a message.
+///
+/// Depth snapshots carry a single `sequence`, for which `start == end`. Incremental messages carry
+/// `sequenceStart` and `sequenceEnd`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// A single `[price, size]` price level.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::parse::Level;
+/// let level = Level::try_from(&serde_json::json!(["2678.35", 12])).unwrap();
+/// assert_eq!(level, Level(2678.35, 12));
+/// assert!(Level::try_from(&serde_json::json!(["2678.35", -1])).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Level(pub f64, pub i64);
+
+impl Level {
+    /// Reads a level array whose price and size sit at the positions given by `layout`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::parse::{Level, LevelLayout};
+    /// let level = Level::parse(&serde_json::json!([12, "2678.35"]), LevelLayout::SIZE_PRICE).unwrap();
+    /// assert_eq!(level, Level(2678.35, 12));
+    /// ```
+    pub fn parse(value: &Value, layout: LevelLayout) -> Result<Self, FeedError> {
+        let needed = layout.price_index.max(layout.size_index) + 1;
+        if value.as_array().is_none_or(|fields| fields.len() < needed) {
+            return Err(FeedError::Parse(format!("not a level of {} fields: {}", needed, value)));
+        }
+
+        let (price, size) = (&value[layout.price_index], &value[layout.size_index]);
+        let price = coerce_f64(price).ok_or_else(|| FeedError::Parse(format!("invalid price: {}", price)))?;
+        let size = coerce_i64(size).ok_or_else(|| FeedError::Parse(format!("invalid size: {}", size)))?;
+        if size < 0 {
+            return Err(FeedError::Parse(format!("negative size: {}", size)));
+        }
+        Ok(Level(price, size))
+    }
+}
+
+impl TryFrom<&Value> for Level {
+    type Error = FeedError;
+
+    /// Reads a `[price, size]` array with [`coerce_f64`] and [`coerce_i64`].
+    ///
+    /// Returns [`FeedError::Parse`] if the value is not an array of at least two entries, either
+    /// entry cannot be coerced, or the size is negative. A size of `0` is accepted.
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        Level::parse(value, LevelLayout::default())
+    }
+}
+
+/// The positions of the price and the size within each level array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelLayout {
+    pub price_index: usize,
+    pub size_index: usize,
+}
+
+impl LevelLayout {
+    /// `[price, size]`, as sent by KuCoin.
+    pub const PRICE_SIZE: LevelLayout = LevelLayout { price_index: 0, size_index: 1 };
+    /// `[size, price]`.
+    pub const SIZE_PRICE: LevelLayout = LevelLayout { price_index: 1, size_index: 0 };
+}
+
+impl Default for LevelLayout {
+    fn default() -> Self {
+        LevelLayout::PRICE_SIZE
+    }
+}
+
+/// Bid and ask levels extracted from a KuCoin depth message.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedDepth {
+    pub bids: Vec<(f64, i64)>,
+    pub asks: Vec<(f64, i64)>,
+    /// The sequence numbers covered by the message, if present.
+    pub sequence: Option<SequenceRange>,
+    /// The event timestamp of the message in milliseconds, if present.
+    pub timestamp: Option<i64>,
+    /// Entries that could not be read as a `[price, size]` level, or had a negative size.
+    pub rejected: usize,
+}
+
+/// The keys under `data` holding the bid and ask levels of a depth message.
+///
+/// KuCoin uses `bids`/`asks`, but some endpoints and other venues send `Bids`/`Asks` or `b`/`a`.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::parse::{parse_depth_with, DepthKeys};
+/// let msg = serde_json::json!({"data": {"b": [["2000.0", 1]], "a": [["2001.0", 2]]}});
+/// let parsed = parse_depth_with(&msg, 5, DepthKeys::ABBREVIATED, Default::default());
+/// assert_eq!(parsed.bids, vec![(2000.0, 1)]);
+/// assert_eq!(parsed.asks, vec![(2001.0, 2)]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthKeys {
+    pub bids: &'static str,
+    pub asks: &'static str,
+}
+
+impl DepthKeys {
+    /// `bids` and `asks`, as sent by KuCoin.
+    pub const LOWERCASE: DepthKeys = DepthKeys { bids: "bids", asks: "asks" };
+    /// `Bids` and `Asks`.
+    pub const CAPITALIZED: DepthKeys = DepthKeys { bids: "Bids", asks: "Asks" };
+    /// `b` and `a`.
+    pub const ABBREVIATED: DepthKeys = DepthKeys { bids: "b", asks: "a" };
+}
+
+impl Default for DepthKeys {
+    fn default() -> Self {
+        DepthKeys::LOWERCASE
+    }
+}
+
+/// Extracts up to `depth` bid and ask levels from a KuCoin depth message.
+///
+/// Levels are read from `data.bids` and `data.asks`; see [`parse_depth_with`] for other key names. Prices are parsed as `f64` and sizes as `i64`,
+/// whether they are sent as JSON numbers or strings (see [`coerce_f64`] and [`coerce_i64`]).
+/// Levels whose price or size cannot be read are counted as rejected. A size of `0` is kept as a removal signal,
+/// while levels with a negative size are meaningless and counted as rejected.
+///
+/// # Example JSON Input
+///
+/// ```json
+/// {
+///   "data": {
+///     "bids": [["60000.0", "1"], ["59950.0", "2"]],
+///     "asks": [["60100.0", "1"], ["60150.0", "3"]]
+///   }
+/// }
+/// ```
+pub fn parse_depth(json_data: &Value, depth: usize) -> ParsedDepth {
+    parse_depth_with(json_data, depth, DepthKeys::default(), LevelLayout::default())
+}
+
+/// Like [`parse_depth`], but reads the levels from the `keys` under `data`, with the price and
+/// size of each level at the positions of `layout`.
+pub fn parse_depth_with(json_data: &Value, depth: usize, keys: DepthKeys, layout: LevelLayout) -> ParsedDepth {
+    let mut parsed = ParsedDepth::default();
+
+    // Parse bids & asks manually (for performance)
+    if let Some(bid_array) = json_data["data"][keys.bids].as_array() {
+        parsed.bids = parse_levels(bid_array, depth, layout, &mut parsed.rejected);
+    }
+
+    if let Some(ask_array) = json_data["data"][keys.asks].as_array() {
+        parsed.asks = parse_levels(ask_array, depth, layout, &mut parsed.rejected);
+    }
+
+    parsed.sequence = parse_sequence(&json_data["data"]);
+    parsed.timestamp = parse_timestamp(&json_data["data"]);
+
+    parsed
+}
+
+/// Extracts the level changes of a KuCoin spot level2 message, found under `data.changes`.
+///
+/// Unlike a depth snapshot, each entry is a delta to apply on top of the book: a size of `0`
+/// removes the level. Every change is kept, regardless of depth. Returns `None` if the message
+/// has no `data.changes` object, so snapshots fall through to [`parse_depth_with`].
+///
+/// # Example JSON Input
+///
+/// ```json
+/// {
+///   "data": {
+///     "changes": {"asks": [["18906", "0", "1590"]], "bids": [["18904", "2", "1591"]]},
+///     "sequenceStart": 1590,
+///     "sequenceEnd": 1591
+///   }
+/// }
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::parse::{parse_changes_with, SequenceRange};
+/// let msg = serde_json::json!({"data": {"changes": {"asks": [["18906", "0", "1590"]], "bids": []}, "sequence": 1590}});
+/// let parsed = parse_changes_with(&msg, Default::default(), Default::default()).unwrap();
+/// assert_eq!(parsed.asks, vec![(18906.0, 0)]);
+/// assert_eq!(parsed.sequence, Some(SequenceRange { start: 1590, end: 1590 }));
+/// ```
+pub fn parse_changes_with(json_data: &Value, keys: DepthKeys, layout: LevelLayout) -> Option<ParsedDepth> {
+    let changes = json_data["data"]["changes"].as_object()?;
+    let mut parsed = ParsedDepth::default();
+
+    if let Some(bid_array) = changes.get(keys.bids).and_then(Value::as_array) {
+        parsed.bids = parse_levels(bid_array, usize::MAX, layout, &mut parsed.rejected);
+    }
+
+    if let Some(ask_array) = changes.get(keys.asks).and_then(Value::as_array) {
+        parsed.asks = parse_levels(ask_array, usize::MAX, layout, &mut parsed.rejected);
+    }
+
+    parsed.sequence = parse_sequence(&json_data["data"]);
+    parsed.timestamp = parse_timestamp(&json_data["data"]);
+
+    Some(parsed)
+}
+
+/// Reads a JSON number, or a string holding one, as an `f64`.
+///
+/// Returns `None` for anything else, including `NaN` and infinities, instead of defaulting to zero.
+/// A string with more precision than an `f64` can hold is still read, but logged at debug level
+/// (see [`loses_precision`]).
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::parse::coerce_f64;
+/// assert_eq!(coerce_f64(&serde_json::json!("2678.35")), Some(2678.35));
+/// assert_eq!(coerce_f64(&serde_json::json!(null)), None);
+/// ```
+pub fn coerce_f64(v: &Value) -> Option<f64> {
+    v.as_f64()
+        .or_else(|| v.as_str().and_then(parse_f64_str))
+        .filter(|value| value.is_finite())
+}
+
+fn parse_f64_str(text: &str) -> Option<f64> {
+    let value = text.parse::<f64>().ok()?;
+    if loses_precision(text, value) {
+        log::debug!(
+            "⚠️ {} cannot be represented exactly as f64 (read as {}), consider a decimal type for this contract",
+            text, value
+        );
+    }
+    Some(value)
+}
+
+/// Returns `true` if `value`, parsed from the decimal string `text`, no longer holds all of its digits.
+///
+/// Trailing zeros are not significant. Strings in exponent notation are not checked.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::parse::loses_precision;
+/// assert!(!loses_precision("2678.350", 2678.35));
+/// assert!(loses_precision("0.12345678901234567891", 0.12345678901234567891));
+/// ```
+pub fn loses_precision(text: &str, value: f64) -> bool {
+    let text = text.trim().trim_start_matches('+');
+    if text.contains(['e', 'E']) {
+        return false;
+    }
+    normalize_decimal(text) != normalize_decimal(&value.to_string())
+}
+
+/// Strips insignificant leading and trailing zeros from a plain decimal string.
+fn normalize_decimal(text: &str) -> String {
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", text),
+    };
+    let digits = digits.trim_start_matches('0');
+    let digits = match digits.contains('.') {
+        true => digits.trim_end_matches('0').trim_end_matches('.'),
+        false => digits,
+    };
+    format!("{}{}", sign, digits)
+}
+
+/// Reads a JSON integer, or a string holding one, as an `i64`.
+///
+/// Numbers and strings written with a fraction or an exponent, such as `1e2` or `"1.2E1"`, are
+/// read as an `f64` and accepted only when they hold a whole number within range of `i64`.
+/// Fractional values are rejected rather than rounded, whichever form they come in. Returns `None`
+/// for anything else instead of defaulting to zero.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::parse::coerce_i64;
+/// assert_eq!(coerce_i64(&serde_json::json!("1e2")), Some(100));
+/// assert_eq!(coerce_i64(&serde_json::json!(1e2)), Some(100));
+/// assert_eq!(coerce_i64(&serde_json::json!("1.5")), None);
+/// ```
+pub fn coerce_i64(v: &Value) -> Option<i64> {
+    match v {
+        Value::Number(number) => number.as_i64().or_else(|| number.as_f64().and_then(whole_i64)),
+        Value::String(text) => text.parse::<i64>().ok().or_else(|| text.parse::<f64>().ok().and_then(whole_i64)),
+        _ => None,
+    }
+}
+
+/// `value` as an `i64` if it is a whole number within range.
+fn whole_i64(value: f64) -> Option<i64> {
+    // `as` saturates, so out-of-range values are rejected rather than clamped
+    let in_range = value >= i64::MIN as f64 && value < i64::MAX as f64;
+    (value.is_finite() && value.fract() == 0.0 && in_range).then_some(value as i64)
+}
+
+/// Reads `sequenceStart`/`sequenceEnd`, falling back to a single `sequence`.
+pub fn parse_sequence(data: &Value) -> Option<SequenceRange> {
+    match (data["sequenceStart"].as_u64(), data["sequenceEnd"].as_u64()) {
+        (Some(start), Some(end)) => Some(SequenceRange { start, end }),
+        _ => data["sequence"].as_u64().map(|seq| SequenceRange { start: seq, end: seq }),
+    }
+}
+
+/// Reads `timestamp`, falling back to `ts`, normalized to milliseconds with [`normalize_ts_ms`].
+pub fn parse_timestamp(data: &Value) -> Option<i64> {
+    data["timestamp"].as_i64().or_else(|| data["ts"].as_i64()).map(normalize_ts_ms)
+}
+
+/// Converts a Unix timestamp in nanoseconds, microseconds or milliseconds to milliseconds.
+///
+/// KuCoin futures reports some timestamps in nanoseconds while spot uses milliseconds. The unit is
+/// told apart by magnitude: anything of 17 digits or more is nanoseconds, 14 to 16 digits is
+/// microseconds and shorter values are taken to already be milliseconds.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::parse::normalize_ts_ms;
+/// assert_eq!(normalize_ts_ms(1739942942550000000), 1739942942550);
+/// assert_eq!(normalize_ts_ms(1739942942550), 1739942942550);
+/// ```
+pub fn normalize_ts_ms(ts: i64) -> i64 {
+    match ts.unsigned_abs() {
+        magnitude if magnitude >= 10_u64.pow(16) => ts / 1_000_000,
+        magnitude if magnitude >= 10_u64.pow(13) => ts / 1_000,
+        _ => ts,
+    }
+}
+
+fn parse_levels(levels: &[Value], depth: usize, layout: LevelLayout, rejected: &mut usize) -> Vec<(f64, i64)> {
+    let mut parsed = vec![];
+
+    for level in levels.iter().take(depth) {
+        match Level::parse(level, layout) {
+            Ok(Level(price, size)) => parsed.push((price, size)),
+            Err(_) => *rejected += 1,
+        }
+    }
+
+    parsed
+}
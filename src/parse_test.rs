@@ -0,0 +1,140 @@
+//!Local testing
+//!This tests the JSON coercion helpers
+
+use serde_json::json;
+
+use crate::parse::{coerce_f64, coerce_i64};
+
+#[test]
+fn coerce_numeric_json() {
+    assert_eq!(coerce_f64(&json!(2678.35)), Some(2678.35));
+    assert_eq!(coerce_f64(&json!(12)), Some(12.0));
+    assert_eq!(coerce_i64(&json!(4356)), Some(4356));
+}
+
+#[test]
+fn coerce_string_json() {
+    assert_eq!(coerce_f64(&json!("2678.35")), Some(2678.35));
+    assert_eq!(coerce_i64(&json!("4356")), Some(4356));
+}
+
+#[test]
+fn coerce_null_is_none() {
+    assert_eq!(coerce_f64(&json!(null)), None);
+    assert_eq!(coerce_i64(&json!(null)), None);
+}
+
+#[test]
+fn coerce_garbage_is_none() {
+    assert_eq!(coerce_f64(&json!("abc")), None);
+    assert_eq!(coerce_f64(&json!([1.0])), None);
+    assert_eq!(coerce_i64(&json!("12abc")), None);
+    assert_eq!(coerce_i64(&json!({"size": 1})), None);
+}
+
+#[test]
+fn coerce_non_finite_is_none() {
+    assert_eq!(coerce_f64(&json!("NaN")), None);
+    assert_eq!(coerce_f64(&json!("inf")), None);
+}
+
+#[test]
+fn timestamps_normalize_to_milliseconds() {
+    use crate::parse::{normalize_ts_ms, parse_timestamp};
+
+    let ms = 1739942942550;
+    assert_eq!(normalize_ts_ms(1739942942550000000), ms);
+    assert_eq!(normalize_ts_ms(1739942942550000), ms);
+    assert_eq!(normalize_ts_ms(ms), ms);
+
+    assert_eq!(parse_timestamp(&json!({"ts": 1739942942550000000_i64})), Some(ms));
+    assert_eq!(parse_timestamp(&json!({"timestamp": ms})), Some(ms));
+    assert_eq!(parse_timestamp(&json!({})), None);
+}
+
+#[test]
+fn level_try_from_coerces_and_rejects() {
+    use crate::error::FeedError;
+    use crate::parse::Level;
+
+    assert_eq!(Level::try_from(&json!([2678.35, 12])).unwrap(), Level(2678.35, 12));
+    assert_eq!(Level::try_from(&json!(["2678.35", "12"])).unwrap(), Level(2678.35, 12));
+    assert_eq!(Level::try_from(&json!(["2678.35", 0])).unwrap(), Level(2678.35, 0));
+
+    for invalid in [json!(null), json!(["2678.35"]), json!(["n/a", 1]), json!([2678.35, "lots"]), json!([2678.35, -4])] {
+        assert!(matches!(Level::try_from(&invalid), Err(FeedError::Parse(_))), "{}", invalid);
+    }
+}
+
+#[test]
+fn high_precision_prices_are_detected() {
+    use crate::parse::loses_precision;
+
+    let text = "97123.123456789012345";
+    assert!(loses_precision(text, text.parse().unwrap()));
+    assert!(loses_precision("0.30000000000000000001", 0.3));
+
+    for exact in ["2678.35", "2678.3500", "+2678.35", "002678.35", "60000", "60000.0", "-1.5", "1e-8"] {
+        assert!(!loses_precision(exact, exact.parse().unwrap()), "{}", exact);
+    }
+}
+
+#[test]
+fn parse_depth_with_alternate_keys() {
+    use crate::parse::{parse_depth, parse_depth_with, DepthKeys};
+
+    let msg = json!({
+        "data": {"b": [["2000.0", 3], ["1999.5", 1]], "a": [["2000.5", 2]], "timestamp": 1700000000000i64}
+    });
+
+    let parsed = parse_depth_with(&msg, 5, DepthKeys::ABBREVIATED, Default::default());
+    assert_eq!(parsed.bids, vec![(2000.0, 3), (1999.5, 1)]);
+    assert_eq!(parsed.asks, vec![(2000.5, 2)]);
+    assert_eq!(parsed.timestamp, Some(1700000000000));
+
+    // The default lowercase keys find nothing in this payload.
+    assert!(parse_depth(&msg, 5).bids.is_empty());
+
+    let capitalized = json!({"data": {"Bids": [[2000.0, 3]], "Asks": [[2000.5, 2]]}});
+    let parsed = parse_depth_with(&capitalized, 5, DepthKeys::CAPITALIZED, Default::default());
+    assert_eq!((parsed.bids.len(), parsed.asks.len()), (1, 1));
+}
+
+#[test]
+fn parse_depth_with_size_before_price() {
+    use crate::parse::{parse_depth_with, DepthKeys, LevelLayout};
+
+    let msg = json!({
+        "data": {"bids": [[12, "2678.35"], ["197", 2677.99]], "asks": [[4356, "2678.36"], [-1, "2678.37"]]}
+    });
+
+    let parsed = parse_depth_with(&msg, 5, DepthKeys::default(), LevelLayout::SIZE_PRICE);
+    assert_eq!(parsed.bids, vec![(2678.35, 12), (2677.99, 197)]);
+    assert_eq!(parsed.asks, vec![(2678.36, 4356)]);
+    assert_eq!(parsed.rejected, 1, "the negative size is still rejected");
+
+    // With the default layout the decimal prices are read as sizes and rejected.
+    let swapped = parse_depth_with(&msg, 5, DepthKeys::default(), LevelLayout::default());
+    assert!(swapped.bids.is_empty());
+    assert_eq!(swapped.rejected, 4);
+}
+
+#[test]
+fn coerce_scientific_notation() {
+    use crate::parse::Level;
+
+    assert_eq!(coerce_f64(&json!("6.0e4")), Some(60000.0));
+    assert_eq!(coerce_i64(&json!("1e2")), Some(100));
+    assert_eq!(coerce_i64(&json!("1.2E1")), Some(12));
+    assert_eq!(coerce_i64(&json!("1e30")), None, "out of range for i64");
+    assert_eq!(coerce_i64(&json!("2.5")), None, "plain decimals are not sizes");
+    assert_eq!(coerce_i64(&json!("1.5")), None);
+    assert_eq!(coerce_i64(&json!("1.5e0")), None, "fractions are rejected in every notation");
+    assert_eq!(coerce_i64(&json!(1e2)), Some(100), "JSON numbers with an exponent are floats");
+    assert_eq!(coerce_i64(&json!(1.5)), None);
+    assert_eq!(coerce_i64(&json!(1e30)), None);
+    assert_eq!(coerce_i64(&json!("inf")), None);
+
+    let level = Level::try_from(&json!(["6.0e4", "1e2"])).unwrap();
+    assert_eq!(level, Level(60000.0, 100));
+}
@@ -0,0 +1,69 @@
+//! Tunnels the WebSocket connection through a SOCKS5 proxy.
+//!
+//! Only the unauthenticated `CONNECT` command is supported. The target host name is sent to the
+//! proxy unresolved, so DNS lookups happen on the proxy's side.
+
+use std::io;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const SOCKS_VERSION: u8 = 5;
+const NO_AUTHENTICATION: u8 = 0;
+const CONNECT: u8 = 1;
+const ADDRESS_IPV4: u8 = 1;
+const ADDRESS_DOMAIN: u8 = 3;
+const ADDRESS_IPV6: u8 = 4;
+
+/// Opens a TCP stream to `host:port` through the SOCKS5 proxy listening at `proxy` (`host:port`).
+///
+/// The returned stream is connected end to end, ready for a TLS or WebSocket handshake.
+///
+/// # Example Usage
+///
+/// ```no_run
+/// # use warmup_project::proxy::socks5_connect;
+/// # async fn run() {
+/// let stream = socks5_connect("127.0.0.1:1080", "ws-api-futures.kucoin.com", 443).await.unwrap();
+/// # }
+/// ```
+pub async fn socks5_connect(proxy: &str, host: &str, port: u16) -> io::Result<TcpStream> {
+    let host_len = u8::try_from(host.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "host name longer than 255 bytes"))?;
+    let mut stream = TcpStream::connect(proxy).await?;
+
+    // Greeting: offer only "no authentication"
+    stream.write_all(&[SOCKS_VERSION, 1, NO_AUTHENTICATION]).await?;
+    let mut choice = [0; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice != [SOCKS_VERSION, NO_AUTHENTICATION] {
+        return Err(socks_error(format!("proxy requires an unsupported authentication method {}", choice[1])));
+    }
+
+    // CONNECT to the host name and port
+    let mut request = vec![SOCKS_VERSION, CONNECT, 0, ADDRESS_DOMAIN, host_len];
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != SOCKS_VERSION || reply[1] != 0 {
+        return Err(socks_error(format!("proxy refused the connection with reply code {}", reply[1])));
+    }
+
+    // Skip the bound address the proxy reports
+    let address_len = match reply[3] {
+        ADDRESS_IPV4 => 4,
+        ADDRESS_IPV6 => 16,
+        ADDRESS_DOMAIN => stream.read_u8().await? as usize,
+        other => return Err(socks_error(format!("unknown address type {}", other))),
+    };
+    let mut bound = vec![0; address_len + 2];
+    stream.read_exact(&mut bound).await?;
+
+    Ok(stream)
+}
+
+fn socks_error(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionRefused, format!("SOCKS5: {}", msg))
+}
@@ -0,0 +1,87 @@
+//!Local testing
+//!This tests the SOCKS5 tunnel against a local mock proxy
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+///Serves `n` SOCKS5 CONNECT requests without authentication, relaying each to its target.
+///Returns the proxy address and, per connection, the `host:port` that was requested
+pub(crate) async fn mock_socks5(n: usize) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+
+    let handle = tokio::spawn(async move {
+        let mut targets = vec![];
+        for _ in 0..n {
+            let (mut client, _) = listener.accept().await.unwrap();
+
+            let mut greeting = [0; 2];
+            client.read_exact(&mut greeting).await.unwrap();
+            let mut methods = vec![0; greeting[1] as usize];
+            client.read_exact(&mut methods).await.unwrap();
+            client.write_all(&[5, 0]).await.unwrap();
+
+            let mut request = [0; 5];
+            client.read_exact(&mut request).await.unwrap();
+            assert_eq!(&request[..4], &[5, 1, 0, 3], "expected a CONNECT to a host name");
+            let mut host = vec![0; request[4] as usize];
+            client.read_exact(&mut host).await.unwrap();
+            let port = client.read_u16().await.unwrap();
+            let target = format!("{}:{}", String::from_utf8(host).unwrap(), port);
+
+            let mut upstream = TcpStream::connect(&target).await.unwrap();
+            client.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0]).await.unwrap();
+            targets.push(target);
+            tokio::spawn(async move {
+                let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+            });
+        }
+        targets
+    });
+    (addr, handle)
+}
+
+#[tokio::test]
+async fn socks5_connect_relays_to_the_target() {
+    use crate::proxy::socks5_connect;
+
+    let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = target.local_addr().unwrap().port();
+    let echo = tokio::spawn(async move {
+        let (mut socket, _) = target.accept().await.unwrap();
+        let mut buf = [0; 4];
+        socket.read_exact(&mut buf).await.unwrap();
+        socket.write_all(&buf).await.unwrap();
+    });
+    let (proxy, targets) = mock_socks5(1).await;
+
+    let mut stream = socks5_connect(&proxy, "localhost", port).await.unwrap();
+    stream.write_all(b"ping").await.unwrap();
+    let mut reply = [0; 4];
+    stream.read_exact(&mut reply).await.unwrap();
+
+    assert_eq!(&reply, b"ping");
+    assert_eq!(targets.await.unwrap(), vec![format!("localhost:{}", port)]);
+    echo.await.unwrap();
+}
+
+#[tokio::test]
+async fn socks5_connect_reports_a_refusal() {
+    use crate::proxy::socks5_connect;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let proxy = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        let (mut client, _) = listener.accept().await.unwrap();
+        let mut greeting = [0; 3];
+        client.read_exact(&mut greeting).await.unwrap();
+        client.write_all(&[5, 0]).await.unwrap();
+        let mut request = vec![0; 5 + "example.com".len() + 2];
+        client.read_exact(&mut request).await.unwrap();
+        // 0x05: connection refused
+        client.write_all(&[5, 5, 0, 1, 0, 0, 0, 0, 0, 0]).await.unwrap();
+    });
+
+    let err = socks5_connect(&proxy, "example.com", 443).await.unwrap_err();
+    assert!(err.to_string().contains("reply code 5"), "{}", err);
+}
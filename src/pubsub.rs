@@ -0,0 +1,33 @@
+//! Publishes snapshots to a Redis pub/sub channel, for fan-out to other services.
+//!
+//! Every snapshot is published as its JSON (see [`crate::snapshot::OrderBookSnapshot::to_json`]).
+
+use redis::AsyncCommands;
+use crate::emit::SnapshotReceiver;
+
+/// Connects to the Redis server at `url` and publishes every snapshot from `receiver` to `channel`.
+///
+/// Returns once the sending half of `receiver` is dropped, or with the first Redis error, e.g.
+/// when the server cannot be reached.
+///
+/// # Example Usage
+///
+/// ```no_run
+/// # use warmup_project::emit::snapshot_channel;
+/// # use warmup_project::pubsub::publish_snapshots;
+/// # async fn run() {
+/// let (tx, rx) = snapshot_channel(64);
+/// tokio::spawn(publish_snapshots("redis://127.0.0.1/", "orderbook:ETHUSDTM", rx));
+/// // Pass `Some(tx)` as the sink of the WebSocket listener.
+/// # drop(tx);
+/// # }
+/// ```
+pub async fn publish_snapshots(url: &str, channel: &str, mut receiver: SnapshotReceiver) -> redis::RedisResult<()> {
+    let client = redis::Client::open(url)?;
+    let mut connection = client.get_multiplexed_async_connection().await?;
+
+    while let Some(snapshot) = receiver.recv().await {
+        let _subscribers: i64 = connection.publish(channel, snapshot.to_json().to_string()).await?;
+    }
+    Ok(())
+}
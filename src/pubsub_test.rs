@@ -0,0 +1,72 @@
+//!Local testing
+//!This tests publishing snapshots to Redis
+
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+use crate::emit::snapshot_channel;
+use crate::pubsub::publish_snapshots;
+use crate::snapshot::OrderBookSnapshot;
+
+///Serves one Redis connection, answering `+OK` to every command and forwarding the channel and
+///message of each `PUBLISH`
+async fn mock_redis() -> (String, mpsc::UnboundedReceiver<(String, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("redis://{}/", listener.local_addr().unwrap());
+    let (published, receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (read, mut write) = socket.into_split();
+        let mut read = BufReader::new(read);
+
+        let mut line = String::new();
+        while read.read_line(&mut line).await.unwrap_or(0) > 0 {
+            let count: usize = line.trim_start_matches('*').trim().parse().unwrap();
+            let mut args = vec![];
+            for _ in 0..count {
+                line.clear();
+                read.read_line(&mut line).await.unwrap();
+                let len: usize = line.trim_start_matches('$').trim().parse().unwrap();
+                let mut arg = vec![0; len + 2];
+                read.read_exact(&mut arg).await.unwrap();
+                args.push(String::from_utf8_lossy(&arg[..len]).to_string());
+            }
+            line.clear();
+
+            if args[0].eq_ignore_ascii_case("PUBLISH") {
+                write.write_all(b":1\r\n").await.unwrap();
+                let _ = published.send((args[1].clone(), args[2].clone()));
+            } else {
+                write.write_all(b"+OK\r\n").await.unwrap();
+            }
+        }
+    });
+    (url, receiver)
+}
+
+#[tokio::test]
+async fn snapshots_are_published_as_json() {
+    let (url, mut published) = mock_redis().await;
+    let (tx, rx) = snapshot_channel(8);
+    let publisher = tokio::spawn(async move { publish_snapshots(&url, "orderbook:ETHUSDTM", rx).await });
+
+    let snapshot = OrderBookSnapshot {
+        symbol: "ETHUSDTM".to_string(),
+        bids: vec![(2000.0, 15)],
+        asks: vec![(2001.0, 3)],
+        timestamp: Some(1739942942550),
+        sequence: Some(7),
+    };
+    tx.try_send(snapshot.clone());
+
+    let (channel, message) = published.recv().await.unwrap();
+    assert_eq!(channel, "orderbook:ETHUSDTM");
+    let json: Value = serde_json::from_str(&message).unwrap();
+    assert_eq!(OrderBookSnapshot::from_json(&json), Some(snapshot));
+
+    drop(tx);
+    publisher.await.unwrap().unwrap();
+}
@@ -0,0 +1,212 @@
+//! Records snapshots as JSON lines, e.g. to replay or analyze a session later.
+//!
+//! Recordings whose file name ends in `.gz` are gzip-compressed transparently, both when
+//! recording with [`Recorder::create`] and when reading them back with [`replay`].
+//!
+//! [`Recorder`] and [`RecordFile`] write with blocking file I/O and compression. Inside the tokio
+//! listener, record through [`record_snapshots`], which runs them on a blocking thread.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use crate::emit::SnapshotReceiver;
+use crate::snapshot::OrderBookSnapshot;
+
+/// Writes snapshots to `writer` as one JSON object per line.
+///
+/// At high update rates only every `record_every`-th snapshot is written, to keep recordings
+/// manageable. [`Recorder::finish`] writes the most recent snapshot if it was skipped, so a
+/// recording always ends with the final state of the book.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::record::Recorder;
+/// # use warmup_project::snapshot::OrderBookSnapshot;
+/// let mut recorder = Recorder::new(Vec::new(), 2);
+/// for _ in 0..3 {
+///     recorder.record(&OrderBookSnapshot::default()).unwrap();
+/// }
+/// let recording = recorder.finish().unwrap();
+/// assert_eq!(String::from_utf8(recording).unwrap().lines().count(), 2);
+/// ```
+pub struct Recorder<W: Write> {
+    writer: W,
+    record_every: usize,
+    seen: usize,
+    skipped: Option<OrderBookSnapshot>,
+}
+
+impl Recorder<RecordFile> {
+    /// Creates a recorder writing to a new file at `path`, gzip-compressed if it ends in `.gz`.
+    ///
+    /// Call [`RecordFile::close`] on the writer returned by [`Recorder::finish`] to complete the
+    /// gzip stream.
+    pub fn create(path: impl AsRef<Path>, record_every: usize) -> io::Result<Self> {
+        Ok(Self::new(RecordFile::create(path)?, record_every))
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// Creates a recorder writing every `record_every`-th snapshot. `0` and `1` record every snapshot.
+    pub fn new(writer: W, record_every: usize) -> Self {
+        Self {
+            writer,
+            record_every: record_every.max(1),
+            seen: 0,
+            skipped: None,
+        }
+    }
+
+    /// Offers a snapshot to the recording.
+    ///
+    /// Returns `true` if it was written, `false` if it was skipped by decimation.
+    pub fn record(&mut self, snapshot: &OrderBookSnapshot) -> io::Result<bool> {
+        self.seen += 1;
+        if self.seen % self.record_every != 0 {
+            self.skipped = Some(snapshot.clone());
+            return Ok(false);
+        }
+
+        self.skipped = None;
+        self.write_line(snapshot)?;
+        Ok(true)
+    }
+
+    /// Writes the most recent snapshot if it was skipped, flushes and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(snapshot) = self.skipped.take() {
+            self.write_line(&snapshot)?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_line(&mut self, snapshot: &OrderBookSnapshot) -> io::Result<()> {
+        writeln!(self.writer, "{}", snapshot.to_json())
+    }
+}
+
+/// A recording file, written through a gzip encoder when its name ends in `.gz`.
+///
+/// Writes block the calling thread; see [`record_snapshots`] for recording from async code.
+pub enum RecordFile {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl RecordFile {
+    /// Creates (or truncates) the file at `path`.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = BufWriter::new(File::create(path)?);
+        Ok(if is_gzip(path) {
+            RecordFile::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            RecordFile::Plain(file)
+        })
+    }
+
+    /// Writes the gzip trailer, if compressed, and flushes the file.
+    pub fn close(self) -> io::Result<()> {
+        match self {
+            RecordFile::Plain(mut file) => file.flush(),
+            RecordFile::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for RecordFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            RecordFile::Plain(file) => file.write(buf),
+            RecordFile::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            RecordFile::Plain(file) => file.flush(),
+            RecordFile::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Records every `record_every`-th snapshot from `receiver` to a new file at `path`, as with
+/// [`Recorder::create`].
+///
+/// The recorder and its gzip encoder do blocking I/O, so they run on tokio's blocking thread pool
+/// via `spawn_blocking` and are fed over a channel, keeping the runtime's worker threads free.
+/// Returns once the sending half of `receiver` is dropped and the recording is complete.
+///
+/// # Example Usage
+///
+/// ```no_run
+/// # use warmup_project::emit::snapshot_channel;
+/// # use warmup_project::record::record_snapshots;
+/// # async fn run() {
+/// let (tx, rx) = snapshot_channel(1024);
+/// tokio::spawn(record_snapshots("session.jsonl.gz", 10, rx));
+/// // Pass `Some(tx)` as the sink of the WebSocket listener.
+/// # drop(tx);
+/// # }
+/// ```
+pub async fn record_snapshots(
+    path: impl Into<PathBuf>,
+    record_every: usize,
+    mut receiver: SnapshotReceiver,
+) -> io::Result<()> {
+    let path = path.into();
+    let (tx, rx) = mpsc::channel::<OrderBookSnapshot>();
+    let writer = tokio::task::spawn_blocking(move || {
+        let mut recorder = Recorder::create(path, record_every)?;
+        for snapshot in rx {
+            recorder.record(&snapshot)?;
+        }
+        recorder.finish()?.close()
+    });
+
+    while let Some(snapshot) = receiver.recv().await {
+        // The writer only hangs up after an I/O error, which joining it reports
+        if tx.send(snapshot).is_err() {
+            break;
+        }
+    }
+    drop(tx);
+    writer.await.map_err(io::Error::other)?
+}
+
+/// Reads every snapshot of a recording, decompressing it if the file name ends in `.gz`.
+///
+/// Blank lines are skipped. A line that is not a snapshot fails with [`io::ErrorKind::InvalidData`].
+pub fn replay(path: impl AsRef<Path>) -> io::Result<Vec<OrderBookSnapshot>> {
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    let reader: Box<dyn BufRead> = if is_gzip(path) {
+        Box::new(BufReader::new(MultiGzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+
+    let mut snapshots = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let snapshot = serde_json::from_str(&line)
+            .ok()
+            .and_then(|value| OrderBookSnapshot::from_json(&value))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("not a snapshot: {}", line)))?;
+        snapshots.push(snapshot);
+    }
+    Ok(snapshots)
+}
+
+fn is_gzip(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "gz")
+}
@@ -0,0 +1,91 @@
+//!Local testing
+//!This tests the decimated snapshot recorder
+
+use serde_json::Value;
+
+use crate::record::Recorder;
+use crate::snapshot::OrderBookSnapshot;
+
+fn snapshot_with_bid(price: f64) -> OrderBookSnapshot {
+    OrderBookSnapshot { bids: vec![(price, 1)], ..Default::default() }
+}
+
+#[test]
+fn records_every_nth_and_the_last_on_finish() {
+    let mut recorder = Recorder::new(Vec::new(), 3);
+    let mut written = 0;
+    for i in 1..=10 {
+        if recorder.record(&snapshot_with_bid(i as f64)).unwrap() {
+            written += 1;
+        }
+    }
+    assert_eq!(written, 3);
+
+    let recording = String::from_utf8(recorder.finish().unwrap()).unwrap();
+    let best_bids: Vec<f64> = recording
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap()["bids"][0][0].as_f64().unwrap())
+        .collect();
+    assert_eq!(best_bids, vec![3.0, 6.0, 9.0, 10.0]);
+}
+
+#[test]
+fn finish_does_not_repeat_a_recorded_snapshot() {
+    let mut recorder = Recorder::new(Vec::new(), 3);
+    for i in 1..=9 {
+        recorder.record(&snapshot_with_bid(i as f64)).unwrap();
+    }
+
+    let recording = String::from_utf8(recorder.finish().unwrap()).unwrap();
+    assert_eq!(recording.lines().count(), 3);
+}
+
+#[test]
+fn gzip_recording_round_trips() {
+    use crate::order_book::OrderBook;
+    use crate::record::replay;
+
+    let path = std::env::temp_dir().join(format!("orderbook-record-test-{}.jsonl.gz", std::process::id()));
+    let mut live = OrderBook::new();
+    let mut recorder = Recorder::create(&path, 1).unwrap();
+    for i in 0..20 {
+        live.update(vec![(2000.0 - i as f64, 5 + i)], vec![(2001.0 + i as f64, 7)]);
+        recorder.record(&live.snapshot()).unwrap();
+    }
+    recorder.finish().unwrap().close().unwrap();
+
+    // The file is compressed, not plain JSON lines.
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(&bytes[..2], &[0x1f, 0x8b]);
+
+    let snapshots = replay(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(snapshots.len(), 20);
+
+    let mut replayed = OrderBook::new();
+    for snapshot in &snapshots {
+        replayed.apply_snapshot(snapshot);
+    }
+    assert_eq!(replayed.snapshot().bids, live.snapshot().bids);
+    assert_eq!(replayed.snapshot().asks, live.snapshot().asks);
+}
+
+#[tokio::test]
+async fn record_snapshots_writes_from_a_channel_off_the_runtime() {
+    use crate::emit::snapshot_channel;
+    use crate::record::{record_snapshots, replay};
+
+    let path = std::env::temp_dir().join(format!("orderbook-record-channel-{}.jsonl.gz", std::process::id()));
+    let (tx, rx) = snapshot_channel(16);
+    let recording = tokio::spawn(record_snapshots(path.clone(), 2, rx));
+    for i in 1..=5 {
+        assert!(!tx.try_send(snapshot_with_bid(i as f64)), "nothing is dropped");
+    }
+    drop(tx);
+    recording.await.unwrap().unwrap();
+
+    let snapshots = replay(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let best_bids: Vec<f64> = snapshots.iter().map(|snapshot| snapshot.bids[0].0).collect();
+    assert_eq!(best_bids, vec![2.0, 4.0, 5.0]);
+}
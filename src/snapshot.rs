@@ -0,0 +1,112 @@
+use serde_json::{json, Value};
+use crate::parse::Level;
+
+/// A point-in-time copy of the order book, suitable for sending to other tasks.
+///
+/// Both sides are stored best price first: bids descending, asks ascending.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OrderBookSnapshot {
+    pub symbol: String,
+    pub bids: Vec<(f64, i64)>,
+    pub asks: Vec<(f64, i64)>,
+    /// The event timestamp of the last applied message, in milliseconds.
+    pub timestamp: Option<i64>,
+    /// The sequence number of the last applied message.
+    pub sequence: Option<u64>,
+}
+
+impl OrderBookSnapshot {
+    /// Serializes the snapshot as a JSON object.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::snapshot::OrderBookSnapshot;
+    /// let snapshot = OrderBookSnapshot {
+    ///     symbol: "ETHUSDTM".to_string(),
+    ///     bids: vec![(2000.0, 15)],
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(
+    ///     snapshot.to_json().to_string(),
+    ///     r#"{"asks":[],"bids":[[2000.0,15]],"sequence":null,"symbol":"ETHUSDTM","timestamp":null}"#
+    /// );
+    /// ```
+    pub fn to_json(&self) -> Value {
+        json!({
+            "symbol": self.symbol,
+            "bids": self.bids,
+            "asks": self.asks,
+            "timestamp": self.timestamp,
+            "sequence": self.sequence,
+        })
+    }
+
+    /// Reads a snapshot back from the JSON written by [`OrderBookSnapshot::to_json`].
+    ///
+    /// Returns `None` if either side is missing or holds a malformed level.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::snapshot::OrderBookSnapshot;
+    /// let snapshot = OrderBookSnapshot { bids: vec![(2000.0, 15)], timestamp: Some(1), ..Default::default() };
+    /// assert_eq!(OrderBookSnapshot::from_json(&snapshot.to_json()), Some(snapshot));
+    /// ```
+    pub fn from_json(value: &Value) -> Option<Self> {
+        let side = |key: &str| -> Option<Vec<(f64, i64)>> {
+            value[key]
+                .as_array()?
+                .iter()
+                .map(|level| Level::try_from(level).ok().map(|Level(price, size)| (price, size)))
+                .collect()
+        };
+
+        Some(Self {
+            symbol: value["symbol"].as_str().unwrap_or_default().to_string(),
+            bids: side("bids")?,
+            asks: side("asks")?,
+            timestamp: value["timestamp"].as_i64(),
+            sequence: value["sequence"].as_u64(),
+        })
+    }
+}
+
+/// The best bid and ask of a snapshot, as yielded by [`crate::api::top_of_book_stream`] each
+/// time the touch moves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopOfBookChange {
+    pub symbol: String,
+    /// The best bid as `(price, size)`, if the bid side holds any level.
+    pub best_bid: Option<(f64, i64)>,
+    /// The best ask as `(price, size)`, if the ask side holds any level.
+    pub best_ask: Option<(f64, i64)>,
+    /// The event timestamp of the update that moved the touch, in milliseconds.
+    pub timestamp: Option<i64>,
+}
+
+impl TopOfBookChange {
+    /// Reads the best bid and ask of `snapshot`.
+    pub fn of(snapshot: &OrderBookSnapshot) -> Self {
+        Self {
+            symbol: snapshot.symbol.clone(),
+            best_bid: snapshot.bids.first().copied(),
+            best_ask: snapshot.asks.first().copied(),
+            timestamp: snapshot.timestamp,
+        }
+    }
+
+    /// Returns `true` if both tops have the same best bid and ask, prices and sizes alike.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::snapshot::{OrderBookSnapshot, TopOfBookChange};
+    /// let before = OrderBookSnapshot { bids: vec![(2000.0, 15)], timestamp: Some(1), ..Default::default() };
+    /// let after = OrderBookSnapshot { bids: vec![(2000.0, 15), (1999.0, 3)], timestamp: Some(2), ..Default::default() };
+    /// assert!(TopOfBookChange::of(&before).same_touch(&TopOfBookChange::of(&after)));
+    /// ```
+    pub fn same_touch(&self, other: &TopOfBookChange) -> bool {
+        self.best_bid == other.best_bid && self.best_ask == other.best_ask
+    }
+}
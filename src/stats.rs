@@ -0,0 +1,86 @@
+use std::time::Instant;
+
+/// Counters describing the activity of a WebSocket listener.
+#[derive(Debug, Clone)]
+pub struct ListenerStats {
+    /// When the connection was established.
+    pub connected_at: Instant,
+    /// Text frames read from the socket.
+    pub messages_received: u64,
+    /// Order book updates applied to the book.
+    pub updates_applied: u64,
+    /// Order book updates skipped because their sequence was already applied, e.g. re-deliveries.
+    pub stale_updates: u64,
+    /// Snapshots handed to the emitter.
+    pub snapshots_emitted: u64,
+    /// Stale snapshots discarded because the consumer fell behind.
+    pub snapshots_dropped: u64,
+    /// Times the WebSocket token was refreshed on a new connection before it expired.
+    pub token_refreshes: u64,
+    /// Text frames that were not valid JSON, e.g. truncated over a flaky connection.
+    pub parse_failures: u64,
+    /// Malformed frames received since the last one that parsed.
+    pub consecutive_parse_failures: u64,
+    /// Messages rejected for exceeding `FeedConfig::max_message_size` or `max_frame_size`.
+    pub oversized_messages: u64,
+    /// System or status frames received, such as maintenance notices.
+    pub status_messages: u64,
+    /// Times the book settled: went `FeedConfig::settle_after` without an applied update.
+    pub settled_events: u64,
+    /// Updates in a row that left the book crossed, with the best bid at or above the best ask.
+    pub consecutive_crossed: u64,
+    /// Times a persistently crossed book was resnapshotted and resubscribed, see `FeedConfig::resync_on_crossed`.
+    pub crossed_resyncs: u64,
+    /// Updates buffered during a resnapshot and applied on top of it, not counted in `updates_applied`.
+    pub updates_replayed: u64,
+    /// The raw text of the last processed frame, when `FeedConfig::retain_last_message` is set.
+    pub last_message: Option<String>,
+}
+
+impl ListenerStats {
+    /// Creates a new set of zeroed counters, stamped with the current time.
+    pub fn new() -> Self {
+        Self {
+            connected_at: Instant::now(),
+            messages_received: 0,
+            updates_applied: 0,
+            stale_updates: 0,
+            snapshots_emitted: 0,
+            snapshots_dropped: 0,
+            token_refreshes: 0,
+            parse_failures: 0,
+            consecutive_parse_failures: 0,
+            oversized_messages: 0,
+            status_messages: 0,
+            settled_events: 0,
+            consecutive_crossed: 0,
+            crossed_resyncs: 0,
+            updates_replayed: 0,
+            last_message: None,
+        }
+    }
+
+    /// Zeroes every counter, keeping `connected_at` so the connection age stays correct.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::stats::ListenerStats;
+    /// let mut stats = ListenerStats::new();
+    /// stats.messages_received = 42;
+    /// stats.reset();
+    /// assert_eq!(stats.messages_received, 0);
+    /// ```
+    pub fn reset(&mut self) {
+        *self = Self {
+            connected_at: self.connected_at,
+            ..Self::new()
+        };
+    }
+}
+
+impl Default for ListenerStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,23 @@
+//!Local testing
+//!This tests the listener counters
+
+use crate::stats::ListenerStats;
+
+#[test]
+fn reset_zeroes_counters_but_keeps_connected_at() {
+    let mut stats = ListenerStats::new();
+    let connected_at = stats.connected_at;
+
+    stats.messages_received += 10;
+    stats.updates_applied += 8;
+    stats.snapshots_emitted += 8;
+    stats.snapshots_dropped += 2;
+
+    stats.reset();
+
+    assert_eq!(stats.messages_received, 0);
+    assert_eq!(stats.updates_applied, 0);
+    assert_eq!(stats.snapshots_emitted, 0);
+    assert_eq!(stats.snapshots_dropped, 0);
+    assert_eq!(stats.connected_at, connected_at);
+}
@@ -0,0 +1,73 @@
+//! Recognizes KuCoin system and status frames, such as maintenance notices.
+//!
+//! These frames carry no book data, but warn that the feed may degrade or pause. They are logged
+//! and, when `FeedConfig::on_status` is set, handed to a callback.
+
+use std::fmt;
+use serde_json::Value;
+use crate::callback::Callback;
+
+/// Frame `type`s that carry a system notice rather than market data.
+const STATUS_TYPES: [&str; 2] = ["notice", "status"];
+/// Topic prefixes of system announcements pushed as `message` frames.
+const STATUS_TOPIC_PREFIXES: [&str; 2] = ["/contract/announcement", "/system/"];
+
+/// A system or status frame pushed by the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemStatus {
+    /// The frame's `subject`, e.g. `maintenance`, or its `type` when it has none.
+    pub subject: String,
+    /// The frame's `data` payload.
+    pub data: Value,
+}
+
+impl SystemStatus {
+    /// Recognizes a status frame: a `notice` or `status` frame, or a `message` on an
+    /// announcement topic. Returns `None` for anything else.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use warmup_project::status::SystemStatus;
+    /// let frame = serde_json::json!({
+    ///     "type": "notice",
+    ///     "subject": "maintenance",
+    ///     "data": {"status": "close", "startAt": 1739942942550i64}
+    /// });
+    /// let status = SystemStatus::from_frame(&frame).unwrap();
+    /// assert_eq!(status.subject, "maintenance");
+    /// assert!(SystemStatus::from_frame(&serde_json::json!({"type": "pong"})).is_none());
+    /// ```
+    pub fn from_frame(json_data: &Value) -> Option<SystemStatus> {
+        let kind = json_data["type"].as_str()?;
+        let is_status = STATUS_TYPES.contains(&kind)
+            || (kind == "message"
+                && json_data["topic"]
+                    .as_str()
+                    .is_some_and(|topic| STATUS_TOPIC_PREFIXES.iter().any(|prefix| topic.starts_with(prefix))));
+        if !is_status {
+            return None;
+        }
+
+        Some(SystemStatus {
+            subject: json_data["subject"].as_str().unwrap_or(kind).to_string(),
+            data: json_data["data"].clone(),
+        })
+    }
+}
+
+impl fmt::Display for SystemStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.subject, self.data)
+    }
+}
+
+/// A callback invoked with every [`SystemStatus`] the listener receives.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::status::StatusHandler;
+/// let handler = StatusHandler::new(|status| eprintln!("feed status: {}", status));
+/// ```
+pub type StatusHandler = Callback<SystemStatus>;
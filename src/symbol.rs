@@ -0,0 +1,73 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::error::FeedError;
+
+/// A validated, normalized trading symbol such as `ETHUSDTM` or `BTC-USDT`.
+///
+/// Symbols are trimmed and uppercased. Apart from the `-` separating the base and quote
+/// currencies of spot pairs, only ASCII letters and digits are allowed.
+///
+/// # Examples
+///
+/// ```
+/// # use warmup_project::symbol::Symbol;
+/// let symbol = Symbol::new(" ethusdtm ").unwrap();
+/// assert_eq!(symbol, "ETHUSDTM");
+/// assert!(Symbol::new("ETH/USDT").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(String);
+
+impl Symbol {
+    /// Validates and normalizes `symbol`, returning [`FeedError::InvalidSymbol`] if it is empty
+    /// or contains anything but ASCII letters, digits and `-`.
+    pub fn new(symbol: &str) -> Result<Self, FeedError> {
+        let normalized = symbol.trim().to_ascii_uppercase();
+        let valid = !normalized.is_empty()
+            && !normalized.starts_with('-')
+            && !normalized.ends_with('-')
+            && normalized.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+
+        if !valid {
+            return Err(FeedError::InvalidSymbol(symbol.to_string()));
+        }
+        Ok(Symbol(normalized))
+    }
+
+    /// The normalized symbol.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Symbol {
+    type Err = FeedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Symbol::new(s)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
@@ -0,0 +1,23 @@
+//!Local testing
+//!This tests the symbol validation
+
+use crate::error::FeedError;
+use crate::symbol::Symbol;
+
+#[test]
+fn valid_symbols_are_normalized() {
+    assert_eq!(Symbol::new("ETHUSDTM").unwrap(), "ETHUSDTM");
+    assert_eq!(Symbol::new(" xbtusdtm\n").unwrap(), "XBTUSDTM");
+    assert_eq!("btc-usdt".parse::<Symbol>().unwrap().as_str(), "BTC-USDT");
+}
+
+#[test]
+fn invalid_symbols_are_rejected() {
+    for invalid in ["", "   ", "ETH/USDT", "ETH USDT", "-ETHUSDT", "ETHUSDT-", "ÉTHUSDT", "ETH_USDT"] {
+        assert!(
+            matches!(Symbol::new(invalid), Err(FeedError::InvalidSymbol(_))),
+            "{:?} should be rejected",
+            invalid
+        );
+    }
+}
@@ -0,0 +1,40 @@
+//! Prometheus-friendly metrics, emitted through the `metrics` crate when the `metrics`
+//! feature is enabled. Install any `metrics` recorder (e.g. a Prometheus exporter) to collect them.
+//! Without the feature every function here is a no-op.
+
+use crate::order_book::OrderBook;
+
+/// Total text frames received.
+pub const MESSAGES_RECEIVED: &str = "orderbook_messages_received_total";
+/// Total reconnect attempts.
+pub const RECONNECTS: &str = "orderbook_reconnects_total";
+/// Current spread between the best ask and the best bid.
+pub const SPREAD: &str = "orderbook_spread";
+/// Current mid price.
+pub const MID: &str = "orderbook_mid";
+
+/// Counts one received text frame.
+pub fn record_message(_ob: &OrderBook) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(MESSAGES_RECEIVED, "symbol" => _ob.symbol().to_string()).increment(1);
+}
+
+/// Publishes the spread and mid of the book, when both sides are present.
+pub fn record_book(_ob: &OrderBook) {
+    #[cfg(feature = "metrics")]
+    {
+        let symbol = _ob.symbol().to_string();
+        if let Some(spread) = _ob.spread() {
+            metrics::gauge!(SPREAD, "symbol" => symbol.clone()).set(spread);
+        }
+        if let Some(mid) = _ob.mid() {
+            metrics::gauge!(MID, "symbol" => symbol).set(mid);
+        }
+    }
+}
+
+/// Counts one reconnect attempt.
+pub fn record_reconnect(_ob: &OrderBook) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(RECONNECTS, "symbol" => _ob.symbol().to_string()).increment(1);
+}
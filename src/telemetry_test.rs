@@ -0,0 +1,38 @@
+//!Local testing
+//!This tests the metrics emitted by the listener
+
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+use crate::api::handle_text;
+use crate::config::FeedConfig;
+use crate::order_book::OrderBook;
+use crate::stats::ListenerStats;
+use crate::telemetry::{MESSAGES_RECEIVED, MID, SPREAD};
+
+#[test]
+fn processing_a_message_registers_metrics() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+
+    let mut config = FeedConfig::default();
+    config.print.book = false;
+    let mut ob = OrderBook::with_config(&config);
+    let mut stats = ListenerStats::new();
+    let msg = r#"{"type":"message","topic":"/contractMarket/level2Depth5:ETHUSDTM","data":{"bids":[["100.0",1]],"asks":[["101.0",1]]}}"#;
+
+    metrics::with_local_recorder(&recorder, || {
+        handle_text(&mut ob, msg, &config, &mut stats, None);
+    });
+
+    let metrics: Vec<(String, DebugValue)> = snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, _, _, value)| (key.key().name().to_string(), value))
+        .collect();
+    let value_of = |name: &str| metrics.iter().find(|(n, _)| n == name).map(|(_, v)| v);
+
+    assert_eq!(value_of(MESSAGES_RECEIVED), Some(&DebugValue::Counter(1)));
+    assert_eq!(value_of(SPREAD), Some(&DebugValue::Gauge(1.0.into())));
+    assert_eq!(value_of(MID), Some(&DebugValue::Gauge(100.5.into())));
+}